@@ -214,6 +214,19 @@ fn compile_c_lib(
         c_builder.file(lib_source.join("fastlog.c"));
         c_builder.file("tables.c");
 
+        if env::var("CARGO_FEATURE_CMSIS").is_ok()
+            && env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "arm"
+        {
+            let basic_maths = cmsis_include_dsp.join("../Source/BasicMathFunctions");
+            for file in ["arm_add_f32.c", "arm_mult_f32.c", "arm_scale_f32.c"] {
+                c_builder.file(basic_maths.join(file));
+            }
+            let (cpu, core) = cmsis_core();
+            c_builder.flag(&format!("-mcpu={cpu}"));
+            c_builder.define(core, None);
+            c_builder.define("__FPU_PRESENT", "1U");
+        }
+
         for flag in cc_args.into_iter() {
             c_builder.flag(flag);
         }
@@ -223,6 +236,34 @@ fn compile_c_lib(
     println!("cargo:rustc-link-lib=fastmaths");
 }
 
+/// Pick the CMSIS core define to match the `target-cpu` in RUSTFLAGS. Owl2 is a Cortex-M4 and Owl3 a Cortex-M7,
+/// both use the thumbv7em-none-eabihf target so the triple alone can't tell them apart
+fn cmsis_core() -> (&'static str, &'static str) {
+    let rustflags = env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default();
+    let mut flags = rustflags.split('\x1f');
+    let mut target_cpu = None;
+    while let Some(flag) = flags.next() {
+        let flag = match flag {
+            "-C" => flags.next().unwrap_or_default(),
+            _ => flag.strip_prefix("-C").unwrap_or(flag),
+        };
+        if let Some(cpu) = flag.strip_prefix("target-cpu=") {
+            target_cpu = Some(cpu);
+        }
+    }
+
+    match target_cpu {
+        Some("cortex-m7") => ("cortex-m7", "ARM_MATH_CM7"),
+        Some("cortex-m4") => ("cortex-m4", "ARM_MATH_CM4"),
+        other => {
+            println!(
+                "cargo:warning=cmsis: unknown target-cpu {other:?}, building CMSIS-DSP for cortex-m4"
+            );
+            ("cortex-m4", "ARM_MATH_CM4")
+        }
+    }
+}
+
 fn in_dir(dir: &Path, f: impl FnOnce()) {
     let old_dir = std::env::current_dir().unwrap();
     std::env::set_current_dir(dir).unwrap();
@@ -9,6 +9,9 @@ use syn::{
     ItemFn, LitStr, ReturnType, Type, Visibility,
 };
 
+/// Must match owl_patch::PatchName::MAX_LEN
+const PATCH_NAME_MAX_LEN: usize = 23;
+
 #[proc_macro_attribute]
 pub fn patch(attr: TokenStream, input: TokenStream) -> TokenStream {
     let f = parse_macro_input!(input as ItemFn);
@@ -40,7 +43,16 @@ pub fn patch(attr: TokenStream, input: TokenStream) -> TokenStream {
         _ => return sig_error(&f),
     };
 
-    let patch_name = parse_macro_input!(attr as LitStr).value();
+    let patch_name = parse_macro_input!(attr as LitStr);
+    if patch_name.value().len() > PATCH_NAME_MAX_LEN {
+        return parse::Error::new(
+            patch_name.span(),
+            format!("patch name must be no more than {PATCH_NAME_MAX_LEN} bytes"),
+        )
+        .to_compile_error()
+        .into();
+    }
+    let patch_name = patch_name.value();
     let main_fn = &f.sig.ident;
 
     quote!(
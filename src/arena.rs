@@ -0,0 +1,125 @@
+//! Bump allocator for temporary buffers
+//!
+//! Allocating scratch buffers through the global allocator on every block is slow, and can fragment the heap.
+//! An [Arena] hands out slices from a fixed block of memory instead, by moving a pointer along it, and gives all
+//! the space back at once when [reset].
+//!
+//! ```
+//! use owl_patch::arena::Arena;
+//!
+//! // Allocate the backing memory once, at startup
+//! let mut memory = vec![0u8; 1024];
+//! let mut arena = Arena::new(&mut memory);
+//!
+//! for _block in 0..4 {
+//!     let scratch = arena.alloc_slice::<f32>(32).unwrap();
+//!     let other = arena.alloc_slice::<f32>(32).unwrap();
+//!     scratch.fill(1.0);
+//!     other.copy_from_slice(scratch);
+//!
+//!     // All slices must have gone out of scope before the reset
+//!     arena.reset();
+//! }
+//! ```
+//!
+//! [reset]: Arena::reset
+use core::{cell::Cell, marker::PhantomData, mem, slice};
+
+/// Bump allocator over a caller-provided block of memory, see the [module docs](self)
+pub struct Arena<'a> {
+    start: *mut u8,
+    capacity: usize,
+    used: Cell<usize>,
+    _memory: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> Arena<'a> {
+    /// Create a new arena, using `memory` for its allocations
+    pub fn new(memory: &'a mut [u8]) -> Self {
+        Self {
+            start: memory.as_mut_ptr(),
+            capacity: memory.len(),
+            used: Cell::new(0),
+            _memory: PhantomData,
+        }
+    }
+
+    /// Allocate a slice of `len` default values, correctly aligned for `T`
+    ///
+    /// Returns None if there is not enough space left. Slices never overlap, and stay valid until [reset].
+    /// Only `Copy` types can be allocated, as nothing is ever dropped.
+    ///
+    /// ```
+    /// # use owl_patch::arena::Arena;
+    /// let mut memory = [0u8; 64];
+    /// let arena = Arena::new(&mut memory);
+    ///
+    /// let bytes = arena.alloc_slice::<u8>(3).unwrap();
+    /// let words = arena.alloc_slice::<u32>(4).unwrap();
+    /// let floats = arena.alloc_slice::<f32>(4).unwrap();
+    ///
+    /// assert_eq!(0, words.as_ptr() as usize % core::mem::align_of::<u32>());
+    /// assert_eq!(0, floats.as_ptr() as usize % core::mem::align_of::<f32>());
+    /// assert_eq!(&[0, 0, 0, 0], words);
+    ///
+    /// // Slices don't overlap
+    /// assert!(bytes.as_ptr_range().end as usize <= words.as_ptr() as usize);
+    /// assert!(words.as_ptr_range().end as usize <= floats.as_ptr() as usize);
+    ///
+    /// // Out of space
+    /// assert!(arena.alloc_slice::<f32>(16).is_none());
+    /// ```
+    ///
+    /// [reset]: Arena::reset
+    // Returning &mut from &self is sound here: each call hands out a distinct region of memory, and reset
+    // needs &mut self so can't be called while any slice is still borrowed
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice<T: Copy + Default>(&self, len: usize) -> Option<&mut [T]> {
+        let base = self.start as usize;
+        let offset =
+            (base + self.used.get()).checked_next_multiple_of(mem::align_of::<T>())? - base;
+        let end = offset.checked_add(len.checked_mul(mem::size_of::<T>())?)?;
+        if end > self.capacity {
+            return None;
+        }
+        self.used.set(end);
+
+        // Safety: the region from offset to end is within the memory, correctly aligned, and has not been
+        // handed out since the last reset
+        unsafe {
+            let ptr = self.start.add(offset) as *mut T;
+            for n in 0..len {
+                ptr.add(n).write(T::default());
+            }
+            Some(slice::from_raw_parts_mut(ptr, len))
+        }
+    }
+
+    /// Free all allocations, so the space can be used again
+    ///
+    /// ```
+    /// # use owl_patch::arena::Arena;
+    /// let mut memory = [0u8; 64];
+    /// let mut arena = Arena::new(&mut memory);
+    ///
+    /// let first = arena.alloc_slice::<u8>(64).unwrap().as_ptr();
+    /// assert!(arena.alloc_slice::<u8>(1).is_none());
+    ///
+    /// arena.reset();
+    /// assert_eq!(0, arena.used());
+    /// assert_eq!(first, arena.alloc_slice::<u8>(64).unwrap().as_ptr());
+    /// ```
+    pub fn reset(&mut self) {
+        self.used.set(0);
+    }
+
+    /// Number of bytes allocated since the last reset, including padding for alignment
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// Total size of the memory in bytes
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
@@ -0,0 +1,186 @@
+//! Envelope generators
+
+/// Stage of an [Adsr] envelope
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdsrStage {
+    /// Not running, level is 0.0
+    Idle,
+    /// Rising to 1.0
+    Attack,
+    /// Falling to the sustain level
+    Decay,
+    /// Holding at the sustain level until the gate is released
+    Sustain,
+    /// Falling to 0.0
+    Release,
+}
+
+/// Linear attack / decay / sustain / release envelope
+///
+/// Open the gate to start the attack; the level rises to 1.0, falls to the sustain level, and holds there until the
+/// gate is closed, when it falls to 0.0. Call [process] once per sample. Attack, decay and release are times in
+/// seconds; the decay time is the time taken to fall from 1.0 to the sustain level. A time of 0.0 is instant.
+///
+/// ```
+/// # use owl_patch::envelope::{Adsr, AdsrStage};
+/// // 1 sample = 1ms
+/// let mut env = Adsr::new(1000.0);
+/// env.set_attack(0.004);
+/// env.set_decay(0.002);
+/// env.set_sustain(0.5);
+/// env.set_release(0.005);
+///
+/// env.gate(true);
+/// let attack: Vec<f32> = (0..4).map(|_| env.process()).collect();
+/// assert_eq!(vec![0.25, 0.5, 0.75, 1.0], attack);
+///
+/// let decay: Vec<f32> = (0..3).map(|_| env.process()).collect();
+/// assert_eq!(vec![0.75, 0.5, 0.5], decay);
+/// assert_eq!(AdsrStage::Sustain, env.stage());
+///
+/// env.gate(false);
+/// let release: Vec<f32> = (0..6).map(|_| (env.process() * 10.0).round() / 10.0).collect();
+/// assert_eq!(vec![0.4, 0.3, 0.2, 0.1, 0.0, 0.0], release);
+/// assert_eq!(AdsrStage::Idle, env.stage());
+/// ```
+///
+/// [process]: Adsr::process
+#[derive(Clone, Copy, Debug)]
+pub struct Adsr {
+    sample_rate: f32,
+    attack: f32,
+    decay: f32,
+    sustain: f32,
+    release: f32,
+    stage: AdsrStage,
+    level: f32,
+    step: f32,
+}
+
+impl Adsr {
+    /// Create a new envelope, with 10ms attack, 100ms decay, 0.7 sustain and 200ms release
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            attack: 0.01,
+            decay: 0.1,
+            sustain: 0.7,
+            release: 0.2,
+            stage: AdsrStage::Idle,
+            level: 0.0,
+            step: 0.0,
+        }
+    }
+
+    /// Change the sample rate
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_step();
+    }
+
+    /// Set the attack time in seconds
+    pub fn set_attack(&mut self, seconds: f32) {
+        self.attack = seconds.max(0.0);
+        self.update_step();
+    }
+
+    /// Set the decay time in seconds
+    pub fn set_decay(&mut self, seconds: f32) {
+        self.decay = seconds.max(0.0);
+        self.update_step();
+    }
+
+    /// Set the sustain level, from 0.0 to 1.0
+    pub fn set_sustain(&mut self, level: f32) {
+        self.sustain = level.clamp(0.0, 1.0);
+        if self.stage == AdsrStage::Sustain {
+            self.level = self.sustain;
+        }
+        self.update_step();
+    }
+
+    /// Set the release time in seconds
+    pub fn set_release(&mut self, seconds: f32) {
+        self.release = seconds.max(0.0);
+        self.update_step();
+    }
+
+    /// Open (true) or close (false) the gate. Opening the gate restarts the attack from the current level
+    pub fn gate(&mut self, on: bool) {
+        if on {
+            self.stage = AdsrStage::Attack;
+        } else if self.stage != AdsrStage::Idle {
+            self.stage = AdsrStage::Release;
+        }
+        self.update_step();
+    }
+
+    /// Return to idle immediately, with a level of 0.0
+    pub fn reset(&mut self) {
+        self.stage = AdsrStage::Idle;
+        self.level = 0.0;
+    }
+
+    /// Current stage
+    pub fn stage(&self) -> AdsrStage {
+        self.stage
+    }
+
+    /// Current level, from 0.0 to 1.0
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// True unless the envelope is idle
+    pub fn is_active(&self) -> bool {
+        self.stage != AdsrStage::Idle
+    }
+
+    /// Advance by one sample, returning the new level
+    pub fn process(&mut self) -> f32 {
+        match self.stage {
+            AdsrStage::Idle | AdsrStage::Sustain => {}
+            AdsrStage::Attack => {
+                self.level += self.step;
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = AdsrStage::Decay;
+                    self.update_step();
+                }
+            }
+            AdsrStage::Decay => {
+                self.level -= self.step;
+                if self.level <= self.sustain {
+                    self.level = self.sustain;
+                    self.stage = AdsrStage::Sustain;
+                }
+            }
+            AdsrStage::Release => {
+                self.level -= self.step;
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = AdsrStage::Idle;
+                }
+            }
+        }
+        self.level
+    }
+
+    /// Per-sample change in level for the current stage
+    fn update_step(&mut self) {
+        let (distance, seconds) = match self.stage {
+            AdsrStage::Attack => (1.0, self.attack),
+            AdsrStage::Decay => (1.0 - self.sustain, self.decay),
+            // Release always takes the full release time, from wherever it started
+            AdsrStage::Release => (self.level, self.release),
+            AdsrStage::Idle | AdsrStage::Sustain => return,
+        };
+
+        let samples = seconds * self.sample_rate;
+        self.step = if samples < 1.0 {
+            f32::INFINITY
+        } else {
+            distance / samples
+        };
+    }
+}
@@ -15,8 +15,13 @@
 //! The `fastmaths` crate feature must be enabled if you want to use this module
 //!
 //! [patch]: crate::patch
+use core::f32::consts::{FRAC_PI_2, PI, TAU};
+
 use crate::ffi::fastmaths::*;
 
+#[cfg(target_os = "none")]
+use num_traits::Euclid as _;
+
 /// Fast (approximate) maths functions
 pub trait FastFloat {
     /// Fast (approximate) power: `self^n`
@@ -44,10 +49,34 @@ pub trait FastFloat {
     fn fast_atan2(self, y: Self) -> Self;
 
     /// Fast (approximate) hyperbolic tangent
+    ///
+    /// A [7/6] Padé approximant, within 1e-4 of `tanh`. It is monotonic, and exactly ±1.0 for inputs beyond
+    /// about ±4.97, so it can be used directly as a waveshaper.
     fn fast_tanh(self) -> Self;
 
     /// Fast (approximate) coth: 1/tanh(self)
+    ///
+    /// Uses the same approximation as [fast_tanh](FastFloat::fast_tanh), so is exactly ±1.0 for inputs beyond
+    /// about ±4.97
     fn fast_coth(self) -> Self;
+
+    /// Fast (approximate) sine
+    fn fast_sin(self) -> Self;
+
+    /// Fast (approximate) cosine
+    fn fast_cos(self) -> Self;
+
+    /// Fast (approximate) tangent
+    fn fast_tan(self) -> Self;
+
+    /// Fast (approximate) reciprocal: `1/self`
+    fn fast_recip(self) -> Self;
+
+    /// Fast (approximate) reciprocal square root: `1/sqrt(self)`
+    ///
+    /// Only accurate for normal, positive inputs. 0.0 and denormals give a large, finite value rather than
+    /// infinity, and the result for negative inputs is meaningless, though it won't panic.
+    fn fast_rsqrt(self) -> Self;
 }
 
 impl FastFloat for f32 {
@@ -85,13 +114,58 @@ impl FastFloat for f32 {
     }
     #[inline]
     fn fast_tanh(self) -> Self {
-        let x_squared = self * self;
-        self / (1.0 + (x_squared / (3.0 + (x_squared / (5.0 + (x_squared / 7.0))))))
+        // [7/6] Pade approximant. This is monotonic until it passes 1.0 at x ~= 4.97, so the input is
+        // limited to keep the polynomials in range, and the output clamped to saturate at +-1
+        let x = self.clamp(-5.0, 5.0);
+        let x_squared = x * x;
+        let numerator = x * (135135.0 + x_squared * (17325.0 + x_squared * (378.0 + x_squared)));
+        let denominator =
+            135135.0 + x_squared * (62370.0 + x_squared * (3150.0 + x_squared * 28.0));
+        (numerator / denominator).clamp(-1.0, 1.0)
     }
     #[inline]
     fn fast_coth(self) -> Self {
-        let x_squared = self * self;
-        (1.0 + (x_squared / (3.0 + (x_squared / (5.0 + (x_squared / 7.0)))))) / self
+        1.0 / self.fast_tanh()
+    }
+    #[inline]
+    fn fast_sin(self) -> Self {
+        // The C library has no fast trig functions, so this is a polynomial approximation,
+        // accurate to ~1e-6 after wrapping into (-pi/2..pi/2)
+        let mut x = self.rem_euclid(TAU);
+        if x > PI {
+            x -= TAU;
+        }
+        if x > FRAC_PI_2 {
+            x = PI - x;
+        } else if x < -FRAC_PI_2 {
+            x = -PI - x;
+        }
+        let x_squared = x * x;
+        x * (0.9999966
+            + x_squared * (-0.16664824 + x_squared * (0.00830629 + x_squared * -0.00018363)))
+    }
+    #[inline]
+    fn fast_cos(self) -> Self {
+        (self + FRAC_PI_2).fast_sin()
+    }
+    #[inline]
+    fn fast_tan(self) -> Self {
+        self.fast_sin() / self.fast_cos()
+    }
+    #[inline]
+    fn fast_recip(self) -> Self {
+        // Initial estimate from the bit pattern, refined with two Newton-Raphson steps
+        let y = f32::from_bits(0x7ef311c3u32.wrapping_sub(self.to_bits()));
+        let y = y * (2.0 - self * y);
+        y * (2.0 - self * y)
+    }
+    #[inline]
+    fn fast_rsqrt(self) -> Self {
+        // The "fast inverse square root", with two Newton-Raphson steps
+        let half = self * 0.5;
+        let y = f32::from_bits(0x5f3759dfu32.wrapping_sub(self.to_bits() >> 1));
+        let y = y * (1.5 - half * y * y);
+        y * (1.5 - half * y * y)
     }
 }
 
@@ -184,9 +258,102 @@ mod tests {
         assert_close_enough!(val.fast_tanh(), val.tanh());
     }
 
+    #[test]
+    fn test_fast_tanh_shape() {
+        let mut previous = -1.0f32;
+        for n in -1000..=1000 {
+            let val = n as f32 * 0.01;
+            let tanh = val.fast_tanh();
+            assert!(tanh >= previous, "not monotonic at {val}");
+            assert_eq!(tanh, -(-val).fast_tanh());
+            assert!((tanh - val.tanh()).abs() < 1e-4);
+            previous = tanh;
+        }
+
+        for val in [5.0f32, 10.0, 1000.0, f32::MAX, f32::INFINITY] {
+            assert_eq!(1.0, val.fast_tanh());
+            assert_eq!(-1.0, (-val).fast_tanh());
+        }
+    }
+
+    #[test]
+    fn test_fast_tanh_max_error() {
+        let max_error = (-8000..=8000)
+            .map(|n| n as f32 * 0.001)
+            .map(|val| (val.fast_tanh() - val.tanh()).abs())
+            .fold(0.0, f32::max);
+        assert!(max_error < 1e-4, "max error {max_error}");
+    }
+
     #[test]
     fn test_fast_coth() {
         let val = -1.2;
         assert_close_enough!(val.fast_coth(), 1.0 / val.tanh());
+
+        for n in -1000..=1000 {
+            let val = n as f32 * 0.01;
+            assert_eq!(val.fast_coth(), 1.0 / val.fast_tanh());
+        }
+        assert_eq!(1.0, 10.0f32.fast_coth());
+    }
+
+    #[test]
+    fn test_fast_sin() {
+        for val in [0.3f32, 1.0, 2.5, -2.0, 4.0, -30.7, 1000.1] {
+            assert_close_enough!(val.fast_sin(), val.sin());
+        }
+    }
+
+    #[test]
+    fn test_fast_sin_large_inputs() {
+        for val in [1.0e4f32, -1.0e4, 123456.7, 3.0e9, -3.0e9, 1.0e12, f32::MAX] {
+            let sin = val.fast_sin();
+            assert!((-1.0..=1.0).contains(&sin), "{val}: {sin}");
+        }
+        for n in -100..=100 {
+            let val = n as f32 * 101.3;
+            assert!((val.fast_sin() - val.sin()).abs() < 1e-3, "{val}");
+        }
+    }
+
+    #[test]
+    fn test_fast_cos() {
+        for val in [0.3f32, 1.0, 2.5, -2.0, 4.0, -30.7, 1000.1] {
+            assert_close_enough!(val.fast_cos(), val.cos());
+        }
+    }
+
+    #[test]
+    fn test_fast_tan() {
+        for val in [0.3f32, 1.0, 2.5, -2.0, 4.0, -30.7] {
+            assert_close_enough!(val.fast_tan(), val.tan());
+        }
+    }
+
+    #[test]
+    fn test_fast_recip() {
+        for val in [0.001f32, 0.3, 1.0, 7.5, 440.0, 48000.0, -2.5] {
+            assert_close_enough!(val.fast_recip(), 1.0 / val);
+        }
+    }
+
+    #[test]
+    fn test_fast_rsqrt() {
+        for val in [0.001f32, 0.3, 1.0, 7.5, 440.0, 48000.0] {
+            assert_close_enough!(val.fast_rsqrt(), 1.0 / val.sqrt());
+        }
+    }
+
+    #[test]
+    fn test_fast_rsqrt_edge_cases() {
+        assert!(0.0f32.fast_rsqrt() > 1e18);
+        assert!(1e-40f32.fast_rsqrt() > 1e18);
+        assert_close_enough!(1e30f32.fast_rsqrt(), 1e-15);
+        assert_close_enough!(f32::MAX.fast_rsqrt(), 1.0 / f32::MAX.sqrt());
+
+        // Outside the domain, but mustn't overflow the integer arithmetic
+        for val in [-1.0f32, -0.0, -1e-40, f32::MIN] {
+            let _ = core::hint::black_box(val).fast_rsqrt();
+        }
     }
 }
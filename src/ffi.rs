@@ -66,3 +66,19 @@ pub mod fastmaths {
         pub fn set_default_tables();
     }
 }
+
+#[cfg(all(feature = "cmsis", target_arch = "arm"))]
+pub mod cmsis {
+
+    // Declared manually, matching the CMSIS-DSP basic math functions compiled in build.rs
+
+    extern "C" {
+        pub fn arm_add_f32(pSrcA: *const f32, pSrcB: *const f32, pDst: *mut f32, blockSize: u32);
+    }
+    extern "C" {
+        pub fn arm_mult_f32(pSrcA: *const f32, pSrcB: *const f32, pDst: *mut f32, blockSize: u32);
+    }
+    extern "C" {
+        pub fn arm_scale_f32(pSrc: *const f32, scale: f32, pDst: *mut f32, blockSize: u32);
+    }
+}
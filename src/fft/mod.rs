@@ -0,0 +1,104 @@
+//! Fast Fourier Transform helpers
+//!
+//! Supporting utilities for spectral processing, such as the window functions applied to each block before and
+//! after a transform, and conversions between complex bins and magnitude / phase.
+pub mod window;
+
+use num::Complex;
+
+#[cfg(target_os = "none")]
+use num_traits::Float as _;
+
+/// Lowest level returned by [complex_to_db], for bins with no energy
+pub const MIN_DB: f32 = -120.0;
+
+/// Magnitude of each complex bin
+///
+/// ```
+/// # use owl_patch::fft::*;
+/// # use num::Complex;
+/// let bins = [Complex::new(3.0, 4.0), Complex::new(0.0, -2.0), Complex::new(0.0, 0.0)];
+/// let mut magnitudes = [0.0; 3];
+///
+/// complex_to_magnitude(&bins, &mut magnitudes);
+/// assert_eq!([5.0, 2.0, 0.0], magnitudes);
+/// ```
+///
+/// Panics if the slices are different lengths
+pub fn complex_to_magnitude(input: &[Complex<f32>], output: &mut [f32]) {
+    assert_eq!(input.len(), output.len());
+    for (o, c) in output.iter_mut().zip(input) {
+        *o = (c.re * c.re + c.im * c.im).sqrt();
+    }
+}
+
+/// Phase of each complex bin, in radians from -π to π
+///
+/// ```
+/// # use owl_patch::fft::*;
+/// # use num::Complex;
+/// use core::f32::consts::{FRAC_PI_2, PI};
+///
+/// let bins = [Complex::new(1.0, 0.0), Complex::new(0.0, 2.0), Complex::new(-1.0, 0.0)];
+/// let mut phases = [0.0; 3];
+///
+/// complex_to_phase(&bins, &mut phases);
+/// assert_eq!([0.0, FRAC_PI_2, PI], phases);
+/// ```
+///
+/// Panics if the slices are different lengths
+pub fn complex_to_phase(input: &[Complex<f32>], output: &mut [f32]) {
+    assert_eq!(input.len(), output.len());
+    for (o, c) in output.iter_mut().zip(input) {
+        *o = c.im.atan2(c.re);
+    }
+}
+
+/// Magnitude of each complex bin in decibels (`20 * log10(magnitude)`), no lower than [MIN_DB]
+///
+/// ```
+/// # use owl_patch::fft::*;
+/// # use num::Complex;
+/// let bins = [Complex::new(1.0, 0.0), Complex::new(0.0, 10.0), Complex::new(0.0, 0.0)];
+/// let mut levels = [0.0; 3];
+///
+/// complex_to_db(&bins, &mut levels);
+/// assert_eq!([0.0, 20.0, MIN_DB], levels);
+/// ```
+///
+/// Panics if the slices are different lengths
+pub fn complex_to_db(input: &[Complex<f32>], output: &mut [f32]) {
+    assert_eq!(input.len(), output.len());
+    for (o, c) in output.iter_mut().zip(input) {
+        // 10 * log10(power) == 20 * log10(magnitude), without the sqrt
+        *o = (10.0 * (c.re * c.re + c.im * c.im).log10()).max(MIN_DB);
+    }
+}
+
+/// Complex bins from magnitude and phase (in radians), e.g. to rebuild a spectrum after processing the magnitudes
+///
+/// ```
+/// # use owl_patch::fft::*;
+/// # use num::Complex;
+/// let bins = [Complex::new(3.0f32, 4.0), Complex::new(-1.0, 0.5)];
+/// let mut magnitudes = [0.0; 2];
+/// let mut phases = [0.0; 2];
+/// complex_to_magnitude(&bins, &mut magnitudes);
+/// complex_to_phase(&bins, &mut phases);
+///
+/// let mut rebuilt = [Complex::new(0.0, 0.0); 2];
+/// polar_to_complex(&magnitudes, &phases, &mut rebuilt);
+/// for (a, b) in bins.iter().zip(rebuilt) {
+///     assert!((a - b).norm_sqr() < 1e-12);
+/// }
+/// ```
+///
+/// Panics if the slices are different lengths
+pub fn polar_to_complex(magnitude: &[f32], phase: &[f32], output: &mut [Complex<f32>]) {
+    assert_eq!(magnitude.len(), output.len());
+    assert_eq!(phase.len(), output.len());
+    for ((o, m), p) in output.iter_mut().zip(magnitude).zip(phase) {
+        let (sin, cos) = p.sin_cos();
+        *o = Complex::new(m * cos, m * sin);
+    }
+}
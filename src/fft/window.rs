@@ -0,0 +1,115 @@
+//! Window functions for FFT input / output
+//!
+//! Each generator returns an owned, symmetric window of the requested length, which can then be applied to a
+//! block of samples with [apply_window].
+//!
+//! ```
+//! # use owl_patch::fft::window::*;
+//! let window = hann(8);
+//! let mut samples = [1.0f32; 8];
+//!
+//! apply_window(&mut samples, &window);
+//! assert_eq!(&samples[..], window.samples());
+//! ```
+use core::f32::consts::PI;
+
+#[cfg(target_os = "none")]
+use num_traits::Float as _;
+
+use alloc::boxed::Box;
+
+use crate::sample_buffer::{Buffer, Mono};
+
+/// Rectangular window (all ones)
+///
+/// ```
+/// # use owl_patch::fft::window::*;
+/// assert_eq!(&[1.0; 4], rectangular(4).samples());
+/// ```
+pub fn rectangular(len: usize) -> Buffer<Mono, Box<[f32]>> {
+    cosine_sum(len, &[1.0])
+}
+
+/// Hann window
+///
+/// ```
+/// # use owl_patch::fft::window::*;
+/// let window = hann(9);
+///
+/// assert_eq!(0.0, window[0]);
+/// assert_eq!(1.0, window[4]);
+/// // Symmetric around the centre
+/// window.iter().zip(window.iter().rev()).for_each(|(a, b)| assert!((a - b).abs() < 1e-6));
+/// // A symmetric Hann window of length N sums to (N - 1) / 2
+/// assert!((window.iter().sum::<f32>() - 4.0).abs() < 1e-5);
+/// ```
+pub fn hann(len: usize) -> Buffer<Mono, Box<[f32]>> {
+    cosine_sum(len, &[0.5, 0.5])
+}
+
+/// Hamming window
+///
+/// ```
+/// # use owl_patch::fft::window::*;
+/// let window = hamming(9);
+///
+/// assert!((window[0] - 0.08).abs() < 1e-6);
+/// assert!((window[4] - 1.0).abs() < 1e-6);
+/// window.iter().zip(window.iter().rev()).for_each(|(a, b)| assert!((a - b).abs() < 1e-6));
+/// ```
+pub fn hamming(len: usize) -> Buffer<Mono, Box<[f32]>> {
+    cosine_sum(len, &[0.54, 0.46])
+}
+
+/// Blackman window
+///
+/// ```
+/// # use owl_patch::fft::window::*;
+/// let window = blackman(9);
+///
+/// assert!(window[0].abs() < 1e-6);
+/// assert!((window[4] - 1.0).abs() < 1e-6);
+/// window.iter().zip(window.iter().rev()).for_each(|(a, b)| assert!((a - b).abs() < 1e-6));
+/// ```
+pub fn blackman(len: usize) -> Buffer<Mono, Box<[f32]>> {
+    cosine_sum(len, &[0.42, 0.5, 0.08])
+}
+
+/// Multiply a block of samples by a window, in place
+///
+/// Panics if the lengths don't match
+/// ```should_panic
+/// # use owl_patch::fft::window::*;
+/// let window = hann(8);
+/// let mut samples = [1.0f32; 4];
+///
+/// apply_window(&mut samples, &window);
+/// ```
+pub fn apply_window(samples: &mut [f32], window: &[f32]) {
+    assert_eq!(samples.len(), window.len());
+    for (s, w) in samples.iter_mut().zip(window) {
+        *s *= w;
+    }
+}
+
+/// Generalised cosine window: `w[n] = a0 - a1 * cos(2πn / (N - 1)) + a2 * cos(4πn / (N - 1)) - ...`
+fn cosine_sum(len: usize, coefficients: &[f32]) -> Buffer<Mono, Box<[f32]>> {
+    let mut window = Buffer::new_mono(len);
+    if len == 1 {
+        window[0] = 1.0;
+        return window;
+    }
+
+    let step = 2.0 * PI / (len - 1) as f32;
+    for (n, w) in window.iter_mut().enumerate() {
+        *w = coefficients
+            .iter()
+            .enumerate()
+            .map(|(k, a)| {
+                let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                sign * a * (step * (k * n) as f32).cos()
+            })
+            .sum();
+    }
+    window
+}
@@ -0,0 +1,455 @@
+//! Audio filters
+use crate::sample_buffer::{Buffer, Mono, MutableContainer};
+
+#[cfg(feature = "fastmaths")]
+use crate::fastmaths::FastFloat;
+
+#[cfg(target_os = "none")]
+use num_traits::Float as _;
+
+/// One-pole DC blocking filter
+///
+/// A high-pass with a very low cutoff: `y[n] = x[n] - x[n-1] + R * y[n-1]`
+///
+/// ```
+/// # use owl_patch::filter::DcBlocker;
+/// # use owl_patch::sample_buffer::Buffer;
+/// let mut blocker = DcBlocker::default();
+/// let mut buffer = Buffer::new_mono(4800);
+///
+/// buffer.fill(0.3);
+/// blocker.process_buffer(&mut buffer);
+///
+/// assert_eq!(0.3, buffer[0]);
+/// assert!(buffer[4799].abs() < 1e-3);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DcBlocker {
+    // y[n] = x[n] - x[n-1] + R * y[n-1] is the same as x[n] - lp[n-1], where lp is a one-pole lowpass with
+    // coefficient R
+    lowpass: OnePole,
+}
+
+impl Default for DcBlocker {
+    /// DC blocker with R = 0.995
+    fn default() -> Self {
+        Self::new(0.995)
+    }
+}
+
+impl DcBlocker {
+    /// Create a new DC blocker with coefficient R (should be just under 1.0). Higher values give a lower
+    /// cutoff, but take longer to settle.
+    pub fn new(coefficient: f32) -> Self {
+        Self {
+            lowpass: OnePole::with_coefficient(OnePoleMode::Lowpass, coefficient),
+        }
+    }
+
+    /// Change the coefficient R
+    pub fn set_coefficient(&mut self, coefficient: f32) {
+        self.lowpass.set_coefficient(coefficient);
+    }
+
+    /// The coefficient R
+    pub fn coefficient(&self) -> f32 {
+        self.lowpass.coefficient()
+    }
+
+    /// Filter a single sample
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = input - self.lowpass.state();
+        self.lowpass.process(input);
+        output
+    }
+
+    /// Filter a buffer in place
+    pub fn process_buffer<C: MutableContainer<Item = f32>>(
+        &mut self,
+        buffer: &mut Buffer<Mono, C>,
+    ) {
+        buffer.iter_mut().for_each(|s| *s = self.process(*s));
+    }
+
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.lowpass.reset();
+    }
+}
+
+/// Response of a [OnePole] filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnePoleMode {
+    /// Pass frequencies below the cutoff
+    Lowpass,
+    /// Pass frequencies above the cutoff
+    Highpass,
+}
+
+/// One-pole (6dB / octave) filter
+///
+/// The simplest useful filter, good for smoothing control signals, envelope followers and gentle tone shaping:
+/// `lp[n] = (1 - a) * x[n] + a * lp[n-1]`, with the highpass output being `x[n] - lp[n]`.
+///
+/// ```
+/// # use owl_patch::filter::OnePole;
+/// # #[cfg(feature = "fastmaths")]
+/// # owl_patch::fastmaths::set_default_tables();
+/// let mut lowpass = OnePole::lowpass(100.0, 48000.0);
+/// let mut highpass = OnePole::highpass(100.0, 48000.0);
+///
+/// // DC passes through the lowpass, and is removed by the highpass
+/// let (lp, hp) = (0..48000).fold((0.0, 0.0), |_, _| (lowpass.process(0.5), highpass.process(0.5)));
+/// assert!((lp - 0.5).abs() < 1e-4);
+/// assert!(hp.abs() < 1e-4);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct OnePole {
+    mode: OnePoleMode,
+    coefficient: f32,
+    yn1: f32,
+}
+
+impl OnePole {
+    /// Create a new filter with the given mode, cutoff frequency (Hz) and sample rate
+    pub fn new(mode: OnePoleMode, cutoff: f32, sample_rate: f32) -> Self {
+        let mut filter = Self {
+            mode,
+            coefficient: 0.0,
+            yn1: 0.0,
+        };
+        filter.set_cutoff(cutoff, sample_rate);
+        filter
+    }
+
+    /// Create a new filter with the given mode and pole coefficient `a`, which is clamped to (0.0..1.0)
+    ///
+    /// ```
+    /// # use owl_patch::filter::{OnePole, OnePoleMode};
+    /// // Each step moves halfway to the input
+    /// let mut filter = OnePole::with_coefficient(OnePoleMode::Lowpass, 0.5);
+    /// assert_eq!(0.5, filter.process(1.0));
+    /// assert_eq!(0.75, filter.process(1.0));
+    /// ```
+    pub fn with_coefficient(mode: OnePoleMode, coefficient: f32) -> Self {
+        let mut filter = Self {
+            mode,
+            coefficient: 0.0,
+            yn1: 0.0,
+        };
+        filter.set_coefficient(coefficient);
+        filter
+    }
+
+    /// Create a new lowpass filter
+    pub fn lowpass(cutoff: f32, sample_rate: f32) -> Self {
+        Self::new(OnePoleMode::Lowpass, cutoff, sample_rate)
+    }
+
+    /// Create a new highpass filter
+    pub fn highpass(cutoff: f32, sample_rate: f32) -> Self {
+        Self::new(OnePoleMode::Highpass, cutoff, sample_rate)
+    }
+
+    /// Set the cutoff frequency (Hz). Uses [fast_exp] when the `fastmaths` feature is enabled
+    ///
+    /// A sine at the cutoff frequency is reduced by around 3dB, and higher frequencies by 6dB per octave
+    /// ```
+    /// # use owl_patch::filter::OnePole;
+    /// # #[cfg(feature = "fastmaths")]
+    /// # owl_patch::fastmaths::set_default_tables();
+    /// fn level(mut filter: OnePole, frequency: f32) -> f32 {
+    ///     (0..48000)
+    ///         .map(|n| filter.process((n as f32 * frequency * core::f32::consts::TAU / 48000.0).sin()))
+    ///         .skip(24000)
+    ///         .fold(0.0, |peak: f32, s| peak.max(s.abs()))
+    /// }
+    ///
+    /// let lowpass = OnePole::lowpass(100.0, 48000.0);
+    /// assert!((level(lowpass, 100.0) - core::f32::consts::FRAC_1_SQRT_2).abs() < 0.01);
+    /// assert!(level(lowpass, 10000.0) < 0.02);
+    /// ```
+    ///
+    /// [fast_exp]: crate::fastmaths::FastFloat::fast_exp
+    pub fn set_cutoff(&mut self, cutoff: f32, sample_rate: f32) {
+        let x = -core::f32::consts::TAU * cutoff / sample_rate;
+
+        #[cfg(feature = "fastmaths")]
+        let coefficient = x.fast_exp();
+
+        #[cfg(not(feature = "fastmaths"))]
+        let coefficient = x.exp();
+
+        self.set_coefficient(coefficient);
+    }
+
+    /// Set the pole coefficient `a` directly, clamped to (0.0..1.0). 0.0 passes the input straight through the
+    /// lowpass, values closer to 1.0 give a lower cutoff
+    pub fn set_coefficient(&mut self, coefficient: f32) {
+        self.coefficient = coefficient.clamp(0.0, 1.0);
+    }
+
+    /// Change the mode
+    pub fn set_mode(&mut self, mode: OnePoleMode) {
+        self.mode = mode;
+    }
+
+    /// The current mode
+    pub fn mode(&self) -> OnePoleMode {
+        self.mode
+    }
+
+    /// The pole coefficient `a`
+    pub fn coefficient(&self) -> f32 {
+        self.coefficient
+    }
+
+    /// Filter a single sample
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.yn1 = input + self.coefficient * (self.yn1 - input);
+        match self.mode {
+            OnePoleMode::Lowpass => self.yn1,
+            OnePoleMode::Highpass => input - self.yn1,
+        }
+    }
+
+    /// Filter a buffer in place
+    pub fn process_buffer<C: MutableContainer<Item = f32>>(
+        &mut self,
+        buffer: &mut Buffer<Mono, C>,
+    ) {
+        buffer.iter_mut().for_each(|s| *s = self.process(*s));
+    }
+
+    /// The filter state, which is the most recent lowpass output (whatever the mode)
+    pub fn state(&self) -> f32 {
+        self.yn1
+    }
+
+    /// Set the filter state, e.g. to start a lowpass at a known value instead of rising from 0.0
+    ///
+    /// ```
+    /// # use owl_patch::filter::{OnePole, OnePoleMode};
+    /// let mut filter = OnePole::with_coefficient(OnePoleMode::Lowpass, 0.9);
+    /// filter.set_state(0.5);
+    /// assert_eq!(0.5, filter.process(0.5));
+    /// ```
+    pub fn set_state(&mut self, value: f32) {
+        self.yn1 = value;
+    }
+
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.yn1 = 0.0;
+    }
+}
+
+/// Normalised biquad coefficients (`a0` == 1.0)
+///
+/// The constructors use the formulas from Robert Bristow-Johnson's
+/// [Audio EQ Cookbook](https://www.w3.org/TR/audio-eq-cookbook/)
+///
+/// ```
+/// # use owl_patch::filter::BiquadCoefficients;
+/// let (frequency, q, sample_rate) = (1000.0f32, 0.707, 48000.0);
+/// let coefficients = BiquadCoefficients::lowpass(frequency, q, sample_rate);
+///
+/// let w0 = core::f32::consts::TAU * frequency / sample_rate;
+/// let alpha = w0.sin() / (2.0 * q);
+/// let a0 = 1.0 + alpha;
+///
+/// assert!((coefficients.b0 - (1.0 - w0.cos()) / 2.0 / a0).abs() < 1e-6);
+/// assert!((coefficients.b1 - (1.0 - w0.cos()) / a0).abs() < 1e-6);
+/// assert!((coefficients.b2 - (1.0 - w0.cos()) / 2.0 / a0).abs() < 1e-6);
+/// assert!((coefficients.a1 - -2.0 * w0.cos() / a0).abs() < 1e-6);
+/// assert!((coefficients.a2 - (1.0 - alpha) / a0).abs() < 1e-6);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadCoefficients {
+    /// Feed-forward coefficient for x\[n\]
+    pub b0: f32,
+    /// Feed-forward coefficient for x\[n-1\]
+    pub b1: f32,
+    /// Feed-forward coefficient for x\[n-2\]
+    pub b2: f32,
+    /// Feedback coefficient for y\[n-1\]
+    pub a1: f32,
+    /// Feedback coefficient for y\[n-2\]
+    pub a2: f32,
+}
+
+impl Default for BiquadCoefficients {
+    /// Pass-through (y\[n\] = x\[n\])
+    fn default() -> Self {
+        Self {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+        }
+    }
+}
+
+impl BiquadCoefficients {
+    /// Lowpass filter, with resonance set by `q` (0.707 for a flat response)
+    pub fn lowpass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos_w0, alpha) = Self::intermediates(frequency, q, sample_rate);
+        Self::normalise(
+            [(1.0 - cos_w0) / 2.0, 1.0 - cos_w0, (1.0 - cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// Highpass filter, with resonance set by `q` (0.707 for a flat response)
+    pub fn highpass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos_w0, alpha) = Self::intermediates(frequency, q, sample_rate);
+        Self::normalise(
+            [(1.0 + cos_w0) / 2.0, -(1.0 + cos_w0), (1.0 + cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// Bandpass filter, with a peak gain of 0dB at `frequency`. Higher `q` gives a narrower band
+    pub fn bandpass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos_w0, alpha) = Self::intermediates(frequency, q, sample_rate);
+        Self::normalise(
+            [alpha, 0.0, -alpha],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// Notch (band reject) filter. Higher `q` gives a narrower notch
+    pub fn notch(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        let (cos_w0, alpha) = Self::intermediates(frequency, q, sample_rate);
+        Self::normalise(
+            [1.0, -2.0 * cos_w0, 1.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// Peaking EQ, boosting (or cutting, if negative) by `gain_db` around `frequency`
+    pub fn peaking(frequency: f32, q: f32, sample_rate: f32, gain_db: f32) -> Self {
+        let (cos_w0, alpha) = Self::intermediates(frequency, q, sample_rate);
+        let a = 10.0f32.powf(gain_db / 40.0);
+        Self::normalise(
+            [1.0 + alpha * a, -2.0 * cos_w0, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos_w0, 1.0 - alpha / a],
+        )
+    }
+
+    /// cos(w0) and alpha
+    fn intermediates(frequency: f32, q: f32, sample_rate: f32) -> (f32, f32) {
+        let w0 = core::f32::consts::TAU * frequency / sample_rate;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn normalise([b0, b1, b2]: [f32; 3], [a0, a1, a2]: [f32; 3]) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+}
+
+/// Biquad (2-pole, 2-zero) filter
+///
+/// Processed in transposed direct form II. See [BiquadCoefficients] for the available responses.
+///
+/// ```
+/// # use owl_patch::filter::Biquad;
+/// # use owl_patch::sample_buffer::Buffer;
+/// let mut filter = Biquad::lowpass(1000.0, 0.707, 48000.0);
+/// let mut buffer = Buffer::new_mono(4800);
+///
+/// // DC passes straight through a lowpass
+/// buffer.fill(0.5);
+/// filter.process_buffer(&mut buffer);
+/// assert!((buffer[4799] - 0.5).abs() < 1e-4);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Biquad {
+    coefficients: BiquadCoefficients,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Create a new filter with the given coefficients
+    pub fn new(coefficients: BiquadCoefficients) -> Self {
+        Self {
+            coefficients,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Create a new lowpass filter, see [BiquadCoefficients::lowpass]
+    pub fn lowpass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        Self::new(BiquadCoefficients::lowpass(frequency, q, sample_rate))
+    }
+
+    /// Create a new highpass filter, see [BiquadCoefficients::highpass]
+    pub fn highpass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        Self::new(BiquadCoefficients::highpass(frequency, q, sample_rate))
+    }
+
+    /// Create a new bandpass filter, see [BiquadCoefficients::bandpass]
+    pub fn bandpass(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        Self::new(BiquadCoefficients::bandpass(frequency, q, sample_rate))
+    }
+
+    /// Create a new notch filter, see [BiquadCoefficients::notch]
+    pub fn notch(frequency: f32, q: f32, sample_rate: f32) -> Self {
+        Self::new(BiquadCoefficients::notch(frequency, q, sample_rate))
+    }
+
+    /// Create a new peaking EQ filter, see [BiquadCoefficients::peaking]
+    pub fn peaking(frequency: f32, q: f32, sample_rate: f32, gain_db: f32) -> Self {
+        Self::new(BiquadCoefficients::peaking(
+            frequency,
+            q,
+            sample_rate,
+            gain_db,
+        ))
+    }
+
+    /// Change the coefficients, keeping the current state (so the filter can be swept without clicks)
+    pub fn set_coefficients(&mut self, coefficients: BiquadCoefficients) {
+        self.coefficients = coefficients;
+    }
+
+    /// The current coefficients
+    pub fn coefficients(&self) -> &BiquadCoefficients {
+        &self.coefficients
+    }
+
+    /// Filter a single sample
+    #[inline]
+    pub fn process(&mut self, input: f32) -> f32 {
+        let c = &self.coefficients;
+        let output = c.b0 * input + self.z1;
+        self.z1 = c.b1 * input - c.a1 * output + self.z2;
+        self.z2 = c.b2 * input - c.a2 * output;
+        output
+    }
+
+    /// Filter a buffer in place
+    pub fn process_buffer<C: MutableContainer<Item = f32>>(
+        &mut self,
+        buffer: &mut Buffer<Mono, C>,
+    ) {
+        buffer.iter_mut().for_each(|s| *s = self.process(*s));
+    }
+
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
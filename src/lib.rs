@@ -8,9 +8,16 @@ extern crate alloc;
 mod ffi;
 pub mod midi_message;
 
+pub mod arena;
+pub mod envelope;
 #[cfg(feature = "fastmaths")]
 pub mod fastmaths;
+pub mod fft;
+pub mod filter;
+pub mod interpolation;
+pub mod oscillator;
 pub mod program_vector;
+pub mod resource;
 pub mod sample_buffer;
 pub mod volts_per_octave;
 
@@ -52,6 +59,8 @@ use core::{
 ///
 /// The [ProgramVector] argument contains everything you need to interact with the hardware / os
 ///
+/// The name must fit in the program header, see [PatchName].
+///
 /// [ProgramVector]: crate::program_vector::ProgramVector
 pub use owl_patch_macros::patch;
 
@@ -64,6 +73,77 @@ unsafe fn panic_handler(info: &core::panic::PanicInfo) -> ! {
     }
 }
 
+/// Patch name, as stored in the program header
+///
+/// The header has room for [PatchName::MAX_LEN] bytes plus a nul terminator. Longer names are
+/// truncated (on a char boundary) rather than overrunning the terminator. The [patch] macro rejects
+/// names that are too long at compile time.
+///
+/// ```
+/// # use owl_patch::PatchName;
+/// let long_name = "A very long patch name, thirty";
+/// let name = PatchName::new(long_name);
+///
+/// assert_eq!(30, long_name.len());
+/// assert!(!PatchName::fits(long_name));
+/// assert_eq!(23, name.as_str().len());
+/// assert_eq!("A very long patch name,", name.as_str());
+/// assert_eq!(b"A very long patch name,\0", name.as_c_str().to_bytes_with_nul());
+///
+/// // Multi-byte chars are never split
+/// assert_eq!(22, PatchName::new(&"é".repeat(12)).as_str().len());
+///
+/// assert!(PatchName::fits("Short"));
+/// assert_eq!("Short", PatchName::new("Short").as_str());
+/// ```
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct PatchName([u8; PatchName::MAX_LEN + 1]);
+
+impl PatchName {
+    /// Maximum length of a name in bytes, not including the terminator
+    pub const MAX_LEN: usize = 23;
+
+    /// Create a new patch name, truncating it if it is longer than [PatchName::MAX_LEN] bytes
+    pub const fn new(name: &str) -> Self {
+        let p = name.as_bytes();
+        let mut len = if p.len() > Self::MAX_LEN {
+            Self::MAX_LEN
+        } else {
+            p.len()
+        };
+        // Don't split a multi-byte char
+        while len < p.len() && (p[len] & 0xc0) == 0x80 {
+            len -= 1;
+        }
+
+        let mut bytes = [0u8; Self::MAX_LEN + 1];
+        let mut n = 0;
+        while n < len {
+            bytes[n] = p[n];
+            n += 1;
+        }
+        Self(bytes)
+    }
+
+    /// True if `name` fits without being truncated
+    pub const fn fits(name: &str) -> bool {
+        name.len() <= Self::MAX_LEN
+    }
+
+    /// The name as a string slice
+    pub fn as_str(&self) -> &str {
+        // Only whole chars from a str are ever copied in
+        core::str::from_utf8(self.as_c_str().to_bytes()).unwrap_or_default()
+    }
+
+    /// The name as a nul terminated C string
+    pub fn as_c_str(&self) -> &core::ffi::CStr {
+        // There is always at least one nul, in the last byte
+        core::ffi::CStr::from_bytes_until_nul(&self.0).unwrap_or_default()
+    }
+}
+
 #[doc(hidden)]
 #[repr(C)]
 pub struct ProgramHeader {
@@ -74,7 +154,7 @@ pub struct ProgramHeader {
     stack: *const c_void,
     estack: *const c_void,
     programvector: *const MaybeUninit<FfiProgramVector>,
-    patch_name: [u8; 24],
+    patch_name: PatchName,
 }
 
 unsafe impl Sync for ProgramHeader {}
@@ -93,19 +173,6 @@ impl ProgramHeader {
             static mut _estack: c_void;
         }
 
-        let mut name_bytes = [0u8; 24];
-        let p = patch_name.as_bytes();
-        let len = if p.len() < name_bytes.len() {
-            p.len()
-        } else {
-            name_bytes.len()
-        };
-        let mut n = 0;
-        while n < len {
-            name_bytes[n] = p[n];
-            n += 1;
-        }
-
         ProgramHeader {
             magic_word: Self::MAGIC_WORD,
             start_prog: &raw const _startprog,
@@ -114,12 +181,12 @@ impl ProgramHeader {
             stack: &raw const _stack,
             estack: &raw const _estack,
             programvector,
-            patch_name: name_bytes,
+            patch_name: PatchName::new(patch_name),
         }
     }
 
     pub fn patch_name(&self) -> *const c_char {
-        self.patch_name.as_ptr() as *const c_char
+        self.patch_name.as_c_str().as_ptr()
     }
 }
 
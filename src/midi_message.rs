@@ -1,7 +1,34 @@
 //! Simple midi message implementation, ported directly from <https://github.com/RebelTechnology/OwlProgram/blob/develop/LibSource/MidiMessage.h>
 pub use crate::ffi::midi_message::{MidiStatus, UsbMidi};
+use crate::volts_per_octave::Volts;
+use crate::OpenWareMidiSysexCommand;
+use alloc::vec::Vec;
 use num::FromPrimitive;
 
+// System real-time status bytes
+const TIMING_CLOCK: u8 = 0xf8;
+const START: u8 = 0xfa;
+const CONTINUE: u8 = 0xfb;
+const STOP: u8 = 0xfc;
+
+// SysEx framing bytes
+const SYSEX_START: u8 = 0xf0;
+const SYSEX_END: u8 = 0xf7;
+
+// OpenWare SysEx header bytes, from OpenWareMidiControl.h
+const SYSEX_MANUFACTURER: u8 = 0x7d;
+const SYSEX_OWL_DEVICE: u8 = 0x52;
+
+// Controller numbers for registered / non-registered parameter numbers
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+const NRPN_LSB: u8 = 98;
+const NRPN_MSB: u8 = 99;
+const RPN_LSB: u8 = 100;
+const RPN_MSB: u8 = 101;
+const NULL_PARAMETER_NUMBER: u16 = 0x3fff;
+const RPN_PITCH_BEND_SENSITIVITY: u16 = 0;
+
 /// Simple midi message implementation, ported directly from <https://github.com/RebelTechnology/OwlProgram/blob/develop/LibSource/MidiMessage.h>
 pub struct MidiMessage {
     port: u8,
@@ -77,6 +104,105 @@ impl MidiMessage {
         )
     }
 
+    /// Create the sequence of Control Change messages which set a registered parameter number (RPN) to a
+    /// 14 bit value
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// // Pitch bend range of 12 semitones
+    /// let messages = MidiMessage::rpn(0, 0, 12 << 7);
+    ///
+    /// let controllers = messages.map(|m| (m.controller_number(), m.controller_value()));
+    /// assert_eq!([(101, 0), (100, 0), (6, 12), (38, 0)], controllers);
+    /// ```
+    pub fn rpn(ch: u8, parameter: u16, value: u16) -> [Self; 4] {
+        Self::parameter_number(ch, RPN_MSB, RPN_LSB, parameter, value)
+    }
+
+    /// Create the sequence of Control Change messages which set a non-registered parameter number (NRPN) to
+    /// a 14 bit value
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let messages = MidiMessage::nrpn(3, 0x1234, 0x0abc);
+    ///
+    /// let controllers = messages.map(|m| (m.controller_number(), m.controller_value()));
+    /// assert_eq!([(99, 0x24), (98, 0x34), (6, 0x15), (38, 0x3c)], controllers);
+    /// ```
+    pub fn nrpn(ch: u8, parameter: u16, value: u16) -> [Self; 4] {
+        Self::parameter_number(ch, NRPN_MSB, NRPN_LSB, parameter, value)
+    }
+
+    fn parameter_number(ch: u8, msb: u8, lsb: u8, parameter: u16, value: u16) -> [Self; 4] {
+        [
+            Self::cc(ch, msb, (parameter >> 7) as u8),
+            Self::cc(ch, lsb, parameter as u8),
+            Self::cc(ch, DATA_ENTRY_MSB, (value >> 7) as u8),
+            Self::cc(ch, DATA_ENTRY_LSB, value as u8),
+        ]
+    }
+
+    /// Create a new Timing Clock message (sent 24 times per quarter note)
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let message = MidiMessage::clock();
+    ///
+    /// assert!(message.is_clock());
+    /// assert_eq!(1, message.size());
+    /// assert_eq!([0x0f, 0xf8, 0, 0], message.as_bytes());
+    /// ```
+    pub fn clock() -> Self {
+        Self::real_time(TIMING_CLOCK)
+    }
+
+    /// Create a new Start message
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let message = MidiMessage::start();
+    ///
+    /// assert!(message.is_start());
+    /// assert_eq!(1, message.size());
+    /// assert_eq!([0x0f, 0xfa, 0, 0], message.as_bytes());
+    /// ```
+    pub fn start() -> Self {
+        Self::real_time(START)
+    }
+
+    /// Create a new Stop message
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let message = MidiMessage::stop();
+    ///
+    /// assert!(message.is_stop());
+    /// assert_eq!(1, message.size());
+    /// assert_eq!([0x0f, 0xfc, 0, 0], message.as_bytes());
+    /// ```
+    pub fn stop() -> Self {
+        Self::real_time(STOP)
+    }
+
+    /// Create a new Continue message
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let message = MidiMessage::continue_();
+    ///
+    /// assert!(message.is_continue());
+    /// assert!(!message.is_start());
+    /// assert_eq!(1, message.size());
+    /// assert_eq!([0x0f, 0xfb, 0, 0], message.as_bytes());
+    /// ```
+    pub fn continue_() -> Self {
+        Self::real_time(CONTINUE)
+    }
+
+    fn real_time(status: u8) -> Self {
+        Self::new(UsbMidi::USB_COMMAND_SINGLE_BYTE as u8, status, 0, 0)
+    }
+
     /// Midi port number
     pub fn port(&self) -> u8 {
         self.port >> 4
@@ -137,6 +263,11 @@ impl MidiMessage {
         (self.d1 as u16 | ((self.d2 as u16) << 7)) - 8192
     }
 
+    /// Raw 14 bit pitch bend value, 8192 is centre (valid when is_pitch_bend() == true)
+    pub fn pitch_bend_raw(&self) -> u16 {
+        self.d1 as u16 | ((self.d2 as u16) << 7)
+    }
+
     /// Either a note-on or note-off message
     pub fn is_note(&self) -> bool {
         self.is_note_on() | self.is_note_off()
@@ -158,6 +289,11 @@ impl MidiMessage {
         UsbMidi::from_u8(self.port & 0x0f).map_or(false, |command| command.is_sys_ex())
     }
 
+    /// Is this a system message (no channel)?
+    pub fn is_system(&self) -> bool {
+        self.d0 >= 0xf0
+    }
+
     /// Is this a control change message?
     pub fn is_control_change(&self) -> bool {
         self.status() == MidiStatus::CONTROL_CHANGE
@@ -183,6 +319,26 @@ impl MidiMessage {
         self.status() == MidiStatus::PITCH_BEND_CHANGE
     }
 
+    /// Is this a timing clock message?
+    pub fn is_clock(&self) -> bool {
+        self.d0 == TIMING_CLOCK
+    }
+
+    /// Is this a start message?
+    pub fn is_start(&self) -> bool {
+        self.d0 == START
+    }
+
+    /// Is this a stop message?
+    pub fn is_stop(&self) -> bool {
+        self.d0 == STOP
+    }
+
+    /// Is this a continue message?
+    pub fn is_continue(&self) -> bool {
+        self.d0 == CONTINUE
+    }
+
     /// Raw bytes of message
     pub fn as_bytes(self) -> [u8; 4] {
         [self.port, self.d0, self.d1, self.d2]
@@ -219,3 +375,762 @@ impl UsbMidi {
         )
     }
 }
+
+/// Reassembles SysEx messages which arrive split over several USB midi packets
+///
+/// Feed every received message into [push]; once the packet containing the end-of-exclusive byte arrives,
+/// the message body (without the `F0`/`F7` framing) is returned. Non-SysEx messages are ignored.
+///
+/// ```
+/// # use owl_patch::midi_message::*;
+/// let mut assembler = SysexAssembler::with_capacity(16);
+///
+/// assert_eq!(None, assembler.push(&MidiMessage::new(0x04, 0xf0, 0x01, 0x02)));
+/// assert_eq!(None, assembler.push(&MidiMessage::note_on(0, 60, 100)));
+/// assert_eq!(None, assembler.push(&MidiMessage::new(0x04, 0x03, 0x04, 0x05)));
+/// assert_eq!(
+///     Some(&[0x01, 0x02, 0x03, 0x04, 0x05, 0x06][..]),
+///     assembler.push(&MidiMessage::new(0x06, 0x06, 0xf7, 0x00))
+/// );
+/// ```
+///
+/// [push]: SysexAssembler::push
+#[derive(Default)]
+pub struct SysexAssembler {
+    buffer: Vec<u8>,
+    receiving: bool,
+}
+
+impl SysexAssembler {
+    /// Create a new assembler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new assembler, pre-allocating space for messages up to `capacity` bytes, so that
+    /// nothing needs to be allocated when receiving
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(capacity),
+            receiving: false,
+        }
+    }
+
+    /// Add a received packet, returning the complete message body if this packet ends it
+    ///
+    /// A start byte (`F0`) received mid-message discards whatever was received so far.
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let mut assembler = SysexAssembler::new();
+    ///
+    /// assembler.push(&MidiMessage::new(0x04, 0xf0, 0x01, 0x02));
+    /// assembler.push(&MidiMessage::new(0x04, 0xf0, 0x03, 0x04));
+    /// assert_eq!(Some(&[0x03, 0x04][..]), assembler.push(&MidiMessage::new(0x05, 0xf7, 0x00, 0x00)));
+    ///
+    /// // Continuation packets without a start byte are ignored
+    /// assert_eq!(None, assembler.push(&MidiMessage::new(0x07, 0x05, 0x06, 0xf7)));
+    /// ```
+    pub fn push(&mut self, message: &MidiMessage) -> Option<&[u8]> {
+        let len = match UsbMidi::from_u8(message.port & 0x0f) {
+            Some(UsbMidi::USB_COMMAND_SYSEX | UsbMidi::USB_COMMAND_SYSEX_EOX3) => 3,
+            Some(UsbMidi::USB_COMMAND_SYSEX_EOX2) => 2,
+            Some(UsbMidi::USB_COMMAND_SYSEX_EOX1) => 1,
+            _ => return None,
+        };
+
+        let mut complete = false;
+        for &byte in &[message.d0, message.d1, message.d2][..len] {
+            match byte {
+                SYSEX_START => {
+                    self.buffer.clear();
+                    self.receiving = true;
+                }
+                SYSEX_END => {
+                    complete = self.receiving;
+                    self.receiving = false;
+                }
+                _ if self.receiving => self.buffer.push(byte),
+                _ => {}
+            }
+        }
+
+        complete.then_some(self.buffer.as_slice())
+    }
+}
+
+/// OpenWare device SysEx control message
+///
+/// These are framed as `F0 7D 52 <command> <data...> F7`. Build one and send it with [to_messages], or parse the
+/// body of a received message from [SysexAssembler::push] with [parse].
+///
+/// ```
+/// # use owl_patch::midi_message::*;
+/// # use owl_patch::OpenWareMidiSysexCommand;
+/// let sysex = OpenWareSysex::new(OpenWareMidiSysexCommand::SYSEX_PRESET_NAME_COMMAND, b"Patch");
+/// assert_eq!(
+///     &[0xf0, 0x7d, 0x52, 0x01, b'P', b'a', b't', b'c', b'h', 0xf7][..],
+///     sysex.to_bytes()
+/// );
+///
+/// let mut assembler = SysexAssembler::new();
+/// let mut received = 0;
+/// for message in sysex.to_messages() {
+///     if let Some(body) = assembler.push(&message) {
+///         assert_eq!(Some(sysex), OpenWareSysex::parse(body));
+///         received += 1;
+///     }
+/// }
+/// assert_eq!(1, received);
+/// ```
+///
+/// [to_messages]: OpenWareSysex::to_messages
+/// [parse]: OpenWareSysex::parse
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpenWareSysex<'a> {
+    /// Command type
+    pub command: OpenWareMidiSysexCommand,
+    /// Command data, 7 bit bytes only
+    pub data: &'a [u8],
+}
+
+impl<'a> OpenWareSysex<'a> {
+    /// Create a new message
+    pub fn new(command: OpenWareMidiSysexCommand, data: &'a [u8]) -> Self {
+        Self { command, data }
+    }
+
+    /// Parse a SysEx body (without the `F0` / `F7` framing), as returned by [SysexAssembler::push]
+    ///
+    /// Returns None if the message isn't for an OpenWare device, or the command is not known.
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// # use owl_patch::OpenWareMidiSysexCommand;
+    /// let sysex = OpenWareSysex::parse(&[0x7d, 0x52, 0x21, 0x01, 0x02]).unwrap();
+    ///
+    /// assert_eq!(OpenWareMidiSysexCommand::SYSEX_DEVICE_ID, sysex.command);
+    /// assert_eq!(&[0x01, 0x02], sysex.data);
+    ///
+    /// assert_eq!(None, OpenWareSysex::parse(&[0x7e, 0x52, 0x21]));
+    /// assert_eq!(None, OpenWareSysex::parse(&[0x7d, 0x52, 0x7f]));
+    /// assert_eq!(None, OpenWareSysex::parse(&[0x7d, 0x52]));
+    /// ```
+    pub fn parse(body: &'a [u8]) -> Option<Self> {
+        match body {
+            [SYSEX_MANUFACTURER, SYSEX_OWL_DEVICE, command, data @ ..] => Some(Self {
+                command: OpenWareMidiSysexCommand::from_u8(*command)?,
+                data,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The complete message, including framing. Data bytes are masked to 7 bits
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.data.len() + 5);
+        bytes.extend_from_slice(&[
+            SYSEX_START,
+            SYSEX_MANUFACTURER,
+            SYSEX_OWL_DEVICE,
+            self.command as u8,
+        ]);
+        bytes.extend(self.data.iter().map(|b| b & 0x7f));
+        bytes.push(SYSEX_END);
+        bytes
+    }
+
+    /// The message split into USB midi packets, ready to send
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// # use owl_patch::OpenWareMidiSysexCommand;
+    /// let sysex = OpenWareSysex::new(OpenWareMidiSysexCommand::SYSEX_FIRMWARE_VERSION, &[]);
+    /// let packets: Vec<_> = sysex.to_messages().into_iter().map(MidiMessage::as_bytes).collect();
+    ///
+    /// assert_eq!(vec![[0x04, 0xf0, 0x7d, 0x52], [0x06, 0x20, 0xf7, 0x00]], packets);
+    /// ```
+    pub fn to_messages(&self) -> Vec<MidiMessage> {
+        let bytes = self.to_bytes();
+        let packets = bytes.len().div_ceil(3);
+
+        bytes
+            .chunks(3)
+            .enumerate()
+            .map(|(n, chunk)| {
+                let command = match chunk.len() {
+                    _ if n + 1 < packets => UsbMidi::USB_COMMAND_SYSEX,
+                    1 => UsbMidi::USB_COMMAND_SYSEX_EOX1,
+                    2 => UsbMidi::USB_COMMAND_SYSEX_EOX2,
+                    _ => UsbMidi::USB_COMMAND_SYSEX_EOX3,
+                };
+                let byte = |i: usize| chunk.get(i).copied().unwrap_or(0);
+                MidiMessage::new(command as u8, byte(0), byte(1), byte(2))
+            })
+            .collect()
+    }
+}
+
+/// Registered (RPN) or non-registered (NRPN) parameter number
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterNumberKind {
+    /// Registered parameter number, e.g. pitch bend range
+    Registered,
+    /// Non-registered (manufacturer / device specific) parameter number
+    NonRegistered,
+}
+
+/// A decoded RPN or NRPN value change
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParameterNumberEvent {
+    /// Midi channel
+    pub channel: u8,
+    /// RPN or NRPN
+    pub kind: ParameterNumberKind,
+    /// 14 bit parameter number
+    pub parameter: u16,
+    /// 14 bit value
+    pub value: u16,
+}
+
+#[derive(Clone, Copy, Default)]
+struct ParameterNumberState {
+    kind: Option<ParameterNumberKind>,
+    parameter: u16,
+    value_msb: u8,
+}
+
+impl ParameterNumberState {
+    fn event(&self, channel: u8, value_lsb: u8) -> Option<ParameterNumberEvent> {
+        if self.parameter == NULL_PARAMETER_NUMBER {
+            return None;
+        }
+        self.kind.map(|kind| ParameterNumberEvent {
+            channel,
+            kind,
+            parameter: self.parameter,
+            value: ((self.value_msb as u16) << 7) | value_lsb as u16,
+        })
+    }
+}
+
+/// Decodes RPN / NRPN values from the Control Change messages which carry them
+///
+/// Feed every received message into [push]. The parameter number (CC 99/98 or 101/100) and the data entry
+/// MSB (CC 6) are remembered per channel. A value is returned when the data entry MSB arrives, with the low 7
+/// bits as 0, then again with the full value if the data entry LSB (CC 38) follows. Many senders only send the
+/// MSB, e.g. for pitch bend range.
+///
+/// ```
+/// # use owl_patch::midi_message::*;
+/// let mut parser = ParameterNumberParser::new();
+///
+/// let events: Vec<_> = MidiMessage::nrpn(2, 0x1234, 0x0abc)
+///     .iter()
+///     .filter_map(|m| parser.push(m))
+///     .map(|event| event.value)
+///     .collect();
+/// assert_eq!(vec![0x0a80, 0x0abc], events);
+///
+/// // Pitch bend range of 24 semitones, without the LSB
+/// parser.push(&MidiMessage::cc(0, 101, 0));
+/// parser.push(&MidiMessage::cc(0, 100, 0));
+/// assert_eq!(
+///     Some(ParameterNumberEvent {
+///         channel: 0,
+///         kind: ParameterNumberKind::Registered,
+///         parameter: 0,
+///         value: 24 << 7
+///     }),
+///     parser.push(&MidiMessage::cc(0, 6, 24))
+/// );
+/// ```
+///
+/// [push]: ParameterNumberParser::push
+#[derive(Default)]
+pub struct ParameterNumberParser {
+    channels: [ParameterNumberState; 16],
+}
+
+impl ParameterNumberParser {
+    /// Create a new parser
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a received message, returning the decoded event if this message completes one
+    ///
+    /// Selecting the null parameter (0x3fff) stops further data entry messages producing events
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let mut parser = ParameterNumberParser::new();
+    /// MidiMessage::rpn(0, 0, 2 << 7).iter().for_each(|m| { parser.push(m); });
+    ///
+    /// // Data entry alone adjusts the previously selected parameter
+    /// let event = parser.push(&MidiMessage::cc(0, 38, 1)).unwrap();
+    /// assert_eq!((ParameterNumberKind::Registered, 0, (2 << 7) | 1), (event.kind, event.parameter, event.value));
+    ///
+    /// parser.push(&MidiMessage::cc(0, 101, 0x7f));
+    /// parser.push(&MidiMessage::cc(0, 100, 0x7f));
+    /// assert_eq!(None, parser.push(&MidiMessage::cc(0, 6, 1)));
+    /// assert_eq!(None, parser.push(&MidiMessage::cc(0, 38, 1)));
+    ///
+    /// // Switching between RPN and NRPN starts a new parameter number
+    /// parser.push(&MidiMessage::cc(0, 98, 5));
+    /// assert_eq!(5, parser.push(&MidiMessage::cc(0, 38, 1)).unwrap().parameter);
+    /// ```
+    pub fn push(&mut self, message: &MidiMessage) -> Option<ParameterNumberEvent> {
+        if !message.is_control_change() {
+            return None;
+        }
+
+        let channel = message.channel();
+        let state = &mut self.channels[channel as usize];
+        let value = message.controller_value();
+
+        match message.controller_number() {
+            NRPN_MSB | RPN_MSB | NRPN_LSB | RPN_LSB => {
+                let kind = match message.controller_number() {
+                    NRPN_MSB | NRPN_LSB => ParameterNumberKind::NonRegistered,
+                    _ => ParameterNumberKind::Registered,
+                };
+                if state.kind != Some(kind) {
+                    state.parameter = 0;
+                }
+                state.parameter = match message.controller_number() {
+                    NRPN_MSB | RPN_MSB => (state.parameter & 0x7f) | ((value as u16) << 7),
+                    _ => (state.parameter & !0x7f) | value as u16,
+                };
+                state.kind = Some(kind);
+                None
+            }
+            DATA_ENTRY_MSB => {
+                state.value_msb = value;
+                state.event(channel, 0)
+            }
+            DATA_ENTRY_LSB => state.event(channel, value),
+            _ => None,
+        }
+    }
+}
+
+// Controller number used by MPE for the third (timbre / Y-axis) dimension
+const MPE_TIMBRE: u8 = 74;
+
+/// An active MPE note, with its channel's current expression
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MpeVoice {
+    /// Midi channel
+    pub channel: u8,
+    /// Midi note number
+    pub note: u8,
+    /// Note-on velocity
+    pub velocity: u8,
+    /// Pitch bend (-1.0..1.0)
+    pub bend: f32,
+    /// Pitch bend in semitones, using the channel's bend range
+    pub bend_semitones: f32,
+    /// Channel pressure (0.0..1.0)
+    pub pressure: f32,
+    /// Timbre, CC 74 (0.0..1.0)
+    pub timbre: f32,
+}
+
+#[derive(Clone, Copy)]
+struct MpeExpression {
+    bend_raw: u16,
+    bend_range: PitchBend,
+    pressure: f32,
+    timbre: f32,
+}
+
+impl MpeExpression {
+    fn new(bend_range: f32) -> Self {
+        Self {
+            bend_raw: PitchBend::CENTRE,
+            bend_range: PitchBend::new(bend_range),
+            pressure: 0.0,
+            timbre: 0.5,
+        }
+    }
+}
+
+/// Tracks active notes and per-channel expression from an MPE controller
+///
+/// In MPE each note is played on its own channel, so pitch bend, channel pressure and CC 74 apply to that
+/// note only. Feed every received message into [push], then read the current state from [voices].
+///
+/// Each channel has its own pitch bend range, set by the controller with RPN 0. Until then, the MPE defaults
+/// are used: 48 semitones on the member channels, and 2 on the master channels (0 and 15).
+///
+/// ```
+/// # use owl_patch::midi_message::*;
+/// let mut mpe = MpeState::new();
+///
+/// mpe.push(&MidiMessage::note_on(2, 60, 100));
+/// mpe.push(&MidiMessage::pb(2, 4096));
+/// mpe.push(&MidiMessage::cp(2, 127));
+///
+/// let voice = mpe.voices().next().unwrap();
+/// assert_eq!((2, 60, 100), (voice.channel, voice.note, voice.velocity));
+/// assert_eq!(0.5, voice.bend);
+/// assert!((voice.bend_semitones - 24.0).abs() < 0.01);
+/// assert_eq!(1.0, voice.pressure);
+///
+/// mpe.push(&MidiMessage::note_off(2, 60));
+/// assert_eq!(0, mpe.voices().count());
+/// ```
+///
+/// [push]: MpeState::push
+/// [voices]: MpeState::voices
+pub struct MpeState {
+    notes: Vec<(u8, u8, u8)>,
+    expression: [MpeExpression; 16],
+    parameter_numbers: ParameterNumberParser,
+}
+
+impl Default for MpeState {
+    fn default() -> Self {
+        Self {
+            notes: Vec::new(),
+            expression: core::array::from_fn(|channel| match channel {
+                0 | 15 => MpeExpression::new(2.0),
+                _ => MpeExpression::new(48.0),
+            }),
+            parameter_numbers: ParameterNumberParser::new(),
+        }
+    }
+}
+
+impl MpeState {
+    /// Create a new, empty state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new state, pre-allocating space for `voices` simultaneous notes, so that nothing needs to be
+    /// allocated when receiving
+    pub fn with_capacity(voices: usize) -> Self {
+        Self {
+            notes: Vec::with_capacity(voices),
+            ..Self::default()
+        }
+    }
+
+    /// Update the state from a received message. Messages which aren't relevant are ignored.
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let mut mpe = MpeState::new();
+    ///
+    /// mpe.push(&MidiMessage::note_on(1, 60, 90));
+    /// mpe.push(&MidiMessage::note_on(2, 64, 80));
+    /// mpe.push(&MidiMessage::cc(2, 74, 0));
+    ///
+    /// let timbres: Vec<_> = mpe.voices().map(|v| (v.note, v.timbre)).collect();
+    /// assert_eq!(vec![(60, 0.5), (64, 0.0)], timbres);
+    /// ```
+    pub fn push(&mut self, message: &MidiMessage) {
+        let channel = message.channel();
+        let expression = &mut self.expression[channel as usize];
+
+        if let Some(ParameterNumberEvent {
+            kind: ParameterNumberKind::Registered,
+            parameter: RPN_PITCH_BEND_SENSITIVITY,
+            value,
+            ..
+        }) = self.parameter_numbers.push(message)
+        {
+            // MSB is semitones, LSB cents
+            expression
+                .bend_range
+                .set_range((value >> 7) as f32 + (value & 0x7f) as f32 / 100.0);
+        }
+
+        if message.is_note_on() {
+            self.notes
+                .retain(|&(ch, note, _)| (ch, note) != (channel, message.note()));
+            self.notes
+                .push((channel, message.note(), message.velocity()));
+        } else if message.is_note_off() {
+            self.notes
+                .retain(|&(ch, note, _)| (ch, note) != (channel, message.note()));
+        } else if message.is_pitch_bend() {
+            expression.bend_raw = message.pitch_bend_raw();
+        } else if message.is_channel_pressure() {
+            expression.pressure = message.channel_pressure() as f32 / 127.0;
+        } else if message.is_control_change() && message.controller_number() == MPE_TIMBRE {
+            expression.timbre = message.controller_value() as f32 / 127.0;
+        }
+    }
+
+    /// The pitch bend range of a channel in semitones
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let mut mpe = MpeState::new();
+    /// assert_eq!(2.0, mpe.bend_range(0));
+    /// assert_eq!(48.0, mpe.bend_range(1));
+    ///
+    /// // Set the master channel to 48 semitones, with RPN 0
+    /// MidiMessage::rpn(0, 0, 48 << 7).iter().for_each(|m| mpe.push(m));
+    /// assert_eq!(48.0, mpe.bend_range(0));
+    ///
+    /// mpe.push(&MidiMessage::note_on(0, 60, 100));
+    /// mpe.push(&MidiMessage::pb(0, 8191));
+    /// assert_eq!(48.0, mpe.voices().next().unwrap().bend_semitones);
+    /// mpe.push(&MidiMessage::pb(0, 0));
+    /// assert_eq!(0.0, mpe.voices().next().unwrap().bend_semitones);
+    ///
+    /// // Other channels keep their own range
+    /// MidiMessage::rpn(3, 0, (12 << 7) | 50).iter().for_each(|m| mpe.push(m));
+    /// assert_eq!(12.5, mpe.bend_range(3));
+    /// assert_eq!(48.0, mpe.bend_range(0));
+    /// ```
+    pub fn bend_range(&self, channel: u8) -> f32 {
+        self.expression[channel as usize & 0xf].bend_range.range()
+    }
+
+    /// Iterate over the active notes, oldest first
+    pub fn voices(&self) -> impl Iterator<Item = MpeVoice> + '_ {
+        self.notes.iter().map(|&(channel, note, velocity)| {
+            let expression = self.expression[channel as usize];
+            MpeVoice {
+                channel,
+                note,
+                velocity,
+                bend: expression.bend_raw as f32 / 8192.0 - 1.0,
+                bend_semitones: expression.bend_range.to_note_offset(expression.bend_raw),
+                pressure: expression.pressure,
+                timbre: expression.timbre,
+            }
+        })
+    }
+}
+
+/// Converts between raw 14 bit pitch bend values and pitch, for a given bend range
+///
+/// Full scale in either direction is exactly the bend range, so the centre (8192) is 0.0, 0 is `-range` and
+/// 16383 is `+range`.
+/// ```
+/// # use owl_patch::midi_message::*;
+/// # use owl_patch::volts_per_octave::Volts;
+/// let bend = PitchBend::new(12.0);
+///
+/// assert_eq!(Volts(1.0), bend.to_volts(16383));
+/// assert_eq!(Volts(-1.0), bend.to_volts(0));
+/// assert_eq!(0.0, bend.to_note_offset(8192));
+///
+/// let message = MidiMessage::pb(0, 4096);
+/// assert!((bend.to_note_offset(message.pitch_bend_raw()) - 6.0).abs() < 0.001);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PitchBend {
+    range: f32,
+}
+
+impl Default for PitchBend {
+    /// +-2 semitones, the usual default for synthesizers
+    fn default() -> Self {
+        Self::new(2.0)
+    }
+}
+
+impl PitchBend {
+    const CENTRE: u16 = 8192;
+    const MAX: u16 = 16383;
+
+    /// Create a new converter with a bend range of +-`range` semitones
+    pub fn new(range: f32) -> Self {
+        Self { range }
+    }
+
+    /// Bend range in semitones
+    pub fn range(&self) -> f32 {
+        self.range
+    }
+
+    /// Change the bend range
+    pub fn set_range(&mut self, range: f32) {
+        self.range = range;
+    }
+
+    /// Pitch offset in semitones for a raw value
+    pub fn to_note_offset(&self, raw: u16) -> f32 {
+        let raw = raw.min(Self::MAX);
+        let scale = if raw >= Self::CENTRE {
+            Self::MAX - Self::CENTRE
+        } else {
+            Self::CENTRE
+        };
+        (raw as f32 - Self::CENTRE as f32) / scale as f32 * self.range
+    }
+
+    /// Pitch offset in volts (1V/octave) for a raw value
+    pub fn to_volts(&self, raw: u16) -> Volts {
+        Volts(self.to_note_offset(raw) / 12.0)
+    }
+
+    /// Raw value for a pitch offset in semitones, clamped to the bend range
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let bend = PitchBend::default();
+    ///
+    /// assert_eq!(8192, bend.from_note_offset(0.0));
+    /// assert_eq!(16383, bend.from_note_offset(2.0));
+    /// assert_eq!(0, bend.from_note_offset(-5.0));
+    /// assert_eq!(-1.0, bend.to_note_offset(bend.from_note_offset(-1.0)));
+    /// ```
+    pub fn from_note_offset(&self, semitones: f32) -> u16 {
+        let amount = (semitones / self.range).clamp(-1.0, 1.0);
+        let scale = if amount >= 0.0 {
+            Self::MAX - Self::CENTRE
+        } else {
+            Self::CENTRE
+        };
+        (Self::CENTRE as f32 + amount * scale as f32 + 0.5) as u16
+    }
+
+    /// Raw value for a pitch offset in volts (1V/octave), clamped to the bend range
+    pub fn from_volts(&self, volts: impl Into<Volts>) -> u16 {
+        self.from_note_offset(volts.into().0 * 12.0)
+    }
+}
+
+/// Estimates tempo and beat phase from received midi clock messages
+///
+/// Pass every received message to [push], and call [advance] with the number of samples processed (e.g. once per
+/// audio block) so that the time between clock ticks can be measured. Clock messages are only seen between blocks,
+/// so the measured intervals jitter; they are smoothed before the tempo is calculated.
+///
+/// ```
+/// # use owl_patch::midi_message::*;
+/// // 120 bpm at 48kHz is 24000 samples per beat, so a tick every 1000 samples
+/// let mut clock = ClockTracker::new(48000.0);
+/// assert_eq!(None, clock.bpm());
+///
+/// for n in 0..96 {
+///     clock.push(&MidiMessage::clock());
+///     // uneven block timing
+///     clock.advance(if n % 2 == 0 { 960 } else { 1040 });
+/// }
+/// assert!((clock.bpm().unwrap() - 120.0).abs() < 1.0);
+/// ```
+///
+/// [push]: ClockTracker::push
+/// [advance]: ClockTracker::advance
+#[derive(Clone, Copy, Debug)]
+pub struct ClockTracker {
+    sample_rate: f32,
+    smoothing: f32,
+    samples_since_tick: usize,
+    interval: Option<f32>,
+    ticks: u32,
+    seen_tick: bool,
+    running: bool,
+}
+
+impl ClockTracker {
+    /// Clock ticks per quarter note
+    pub const TICKS_PER_BEAT: u32 = 24;
+
+    /// Create a new tracker for audio running at `sample_rate`
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            smoothing: 0.9,
+            samples_since_tick: 0,
+            interval: None,
+            ticks: 0,
+            seen_tick: false,
+            running: true,
+        }
+    }
+
+    /// Change the sample rate
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Set how heavily tick intervals are smoothed, from 0.0 (none) to just below 1.0 (a lot). Default 0.9
+    pub fn set_smoothing(&mut self, smoothing: f32) {
+        self.smoothing = smoothing.clamp(0.0, 0.999);
+    }
+
+    /// Record that `samples` samples have passed
+    pub fn advance(&mut self, samples: usize) {
+        self.samples_since_tick += samples;
+    }
+
+    /// Handle a received message. Clock, start, stop and continue messages are used, anything else is ignored
+    pub fn push(&mut self, message: &MidiMessage) {
+        if message.is_clock() {
+            self.tick();
+        } else if message.is_start() {
+            self.ticks = 0;
+            self.running = true;
+        } else if message.is_stop() {
+            self.running = false;
+        } else if message.is_continue() {
+            self.running = true;
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.seen_tick {
+            let measured = self.samples_since_tick as f32;
+            self.interval = Some(match self.interval {
+                Some(interval) => interval * self.smoothing + measured * (1.0 - self.smoothing),
+                None => measured,
+            });
+        }
+        self.seen_tick = true;
+        self.samples_since_tick = 0;
+
+        if self.running {
+            self.ticks = self.ticks.wrapping_add(1);
+        }
+    }
+
+    /// Estimated tempo in beats per minute, or None until at least 2 clock ticks have been received
+    pub fn bpm(&self) -> Option<f32> {
+        self.interval
+            .filter(|interval| *interval > 0.0)
+            .map(|interval| 60.0 * self.sample_rate / (interval * Self::TICKS_PER_BEAT as f32))
+    }
+
+    /// Position within the current beat, from 0.0 to 1.0. Interpolated between ticks using the estimated tempo
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::*;
+    /// let mut clock = ClockTracker::new(48000.0);
+    /// clock.push(&MidiMessage::start());
+    /// for _ in 0..30 {
+    ///     clock.advance(1000);
+    ///     clock.push(&MidiMessage::clock());
+    /// }
+    /// clock.advance(500);
+    ///
+    /// // 30.5 ticks in: 6.5 ticks into the second beat
+    /// assert_eq!(30, clock.ticks());
+    /// assert!((clock.phase() - 6.5 / 24.0).abs() < 0.001);
+    /// ```
+    pub fn phase(&self) -> f32 {
+        let between_ticks = match self.interval {
+            Some(interval) if self.running && interval > 0.0 => {
+                (self.samples_since_tick as f32 / interval).min(1.0)
+            }
+            _ => 0.0,
+        };
+        ((self.ticks % Self::TICKS_PER_BEAT) as f32 + between_ticks) / Self::TICKS_PER_BEAT as f32
+    }
+
+    /// Number of clock ticks received while running, since the last start message
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// False after a stop message, until a start or continue message
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+}
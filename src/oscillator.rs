@@ -0,0 +1,347 @@
+//! Oscillators
+use crate::sample_buffer::{Buffer, Mono, MutableContainer};
+
+#[cfg(feature = "fastmaths")]
+use crate::fastmaths::FastFloat;
+
+#[cfg(target_os = "none")]
+use num_traits::Float as _;
+
+/// Phase accumulator, the basis of most oscillators
+///
+/// Phase runs from 0.0 to 1.0, advancing by `frequency / sample_rate` each sample, then wrapping around. Read it
+/// directly with [next], or use one of the waveform readers, which each produce one sample and advance the phase.
+///
+/// ```
+/// # use owl_patch::oscillator::Phasor;
+/// let mut phasor = Phasor::new(48000.0);
+/// phasor.set_frequency(1000.0);
+///
+/// // 1000 cycles in one second
+/// let wraps = (0..48000)
+///     .map(|_| phasor.next())
+///     .collect::<Vec<_>>()
+///     .windows(2)
+///     .filter(|w| w[1] < w[0])
+///     .count();
+/// assert_eq!(999, wraps); // the last cycle ends on the next sample
+/// ```
+///
+/// [next]: Phasor::next
+#[derive(Clone, Copy, Debug)]
+pub struct Phasor {
+    phase: f32,
+    increment: f32,
+    sample_rate: f32,
+}
+
+impl Phasor {
+    /// Create a new phasor at 0 Hz, starting at phase 0.0
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            phase: 0.0,
+            increment: 0.0,
+            sample_rate,
+        }
+    }
+
+    /// Set the frequency in Hz. Negative frequencies run backwards
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.increment = frequency / self.sample_rate;
+    }
+
+    /// Frequency in Hz
+    pub fn frequency(&self) -> f32 {
+        self.increment * self.sample_rate
+    }
+
+    /// Change the sample rate, keeping the same frequency
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        let frequency = self.frequency();
+        self.sample_rate = sample_rate;
+        self.set_frequency(frequency);
+    }
+
+    /// Current phase, from 0.0 to 1.0
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Jump to a phase, e.g. 0.0 to restart on a note on. Values outside 0.0 to 1.0 are wrapped
+    ///
+    /// ```
+    /// # use owl_patch::oscillator::Phasor;
+    /// let mut phasor = Phasor::new(48000.0);
+    /// phasor.set_phase(1.25);
+    /// assert_eq!(0.25, phasor.phase());
+    /// ```
+    pub fn set_phase(&mut self, phase: f32) {
+        self.phase = phase - phase.floor();
+    }
+
+    /// Return the current phase, then advance by one sample
+    ///
+    /// ```
+    /// # use owl_patch::oscillator::Phasor;
+    /// let mut phasor = Phasor::new(4.0);
+    /// phasor.set_frequency(1.0);
+    ///
+    /// let phases: Vec<f32> = (0..6).map(|_| phasor.next()).collect();
+    /// assert_eq!(vec![0.0, 0.25, 0.5, 0.75, 0.0, 0.25], phases);
+    /// ```
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> f32 {
+        let phase = self.phase;
+        self.phase += self.increment;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+        } else if self.phase < 0.0 {
+            self.phase += 1.0;
+        }
+        phase
+    }
+
+    /// Band-limited sawtooth, rising from -1.0 to 1.0
+    ///
+    /// ```
+    /// # use owl_patch::oscillator::Phasor;
+    /// let mut phasor = Phasor::new(48000.0);
+    /// phasor.set_frequency(440.0);
+    ///
+    /// let saw: Vec<f32> = (0..480).map(|_| phasor.saw()).collect();
+    /// assert!(saw.iter().all(|s| (-1.0..=1.0).contains(s)));
+    /// assert!(saw[101] > saw[100]);
+    /// ```
+    pub fn saw(&mut self) -> f32 {
+        let dt = self.increment.abs();
+        let phase = self.next();
+        2.0 * phase - 1.0 - poly_blep(phase, dt)
+    }
+
+    /// Band-limited square wave, 1.0 for the first half of each cycle and -1.0 for the second
+    ///
+    /// ```
+    /// # use owl_patch::oscillator::Phasor;
+    /// let mut phasor = Phasor::new(48000.0);
+    /// phasor.set_frequency(100.0);
+    ///
+    /// let square: Vec<f32> = (0..480).map(|_| phasor.square()).collect();
+    /// assert_eq!(1.0, square[120]);
+    /// assert_eq!(-1.0, square[360]);
+    /// ```
+    pub fn square(&mut self) -> f32 {
+        let dt = self.increment.abs();
+        let phase = self.next();
+        let naive = if phase < 0.5 { 1.0 } else { -1.0 };
+        let half = if phase < 0.5 {
+            phase + 0.5
+        } else {
+            phase - 0.5
+        };
+        naive + poly_blep(phase, dt) - poly_blep(half, dt)
+    }
+
+    /// Sine wave, from -1.0 to 1.0. Uses [fast_sin] when the `fastmaths` feature is enabled
+    ///
+    /// ```
+    /// # use owl_patch::oscillator::Phasor;
+    /// let mut phasor = Phasor::new(4.0);
+    /// phasor.set_frequency(1.0);
+    ///
+    /// let sine: Vec<f32> = (0..4).map(|_| phasor.sine()).collect();
+    /// assert!((sine[1] - 1.0).abs() < 1e-3);
+    /// assert!((sine[3] + 1.0).abs() < 1e-3);
+    /// ```
+    ///
+    /// [fast_sin]: crate::fastmaths::FastFloat::fast_sin
+    pub fn sine(&mut self) -> f32 {
+        let x = self.next() * core::f32::consts::TAU;
+
+        #[cfg(feature = "fastmaths")]
+        return x.fast_sin();
+
+        #[cfg(not(feature = "fastmaths"))]
+        return x.sin();
+    }
+}
+
+/// Waveform shapes for [Lfo]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LfoShape {
+    /// Sine wave, starting at 0.0 and rising
+    Sine,
+    /// Triangle wave, in phase with [LfoShape::Sine]
+    Triangle,
+    /// Rising ramp (sawtooth) from -1.0 to 1.0
+    Ramp,
+    /// A new random value at the start of every cycle
+    SampleAndHold,
+}
+
+/// Low frequency oscillator, for modulation
+///
+/// Timing depends only on the frequency and sample rate, not the block size. Use [process] for a sample at a
+/// time, or [process_block] to fill a buffer. All shapes run from -1.0 to 1.0. The waveforms are not
+/// band-limited, so are not suitable for audio rates.
+///
+/// ```
+/// # use owl_patch::oscillator::{Lfo, LfoShape};
+/// let mut lfo = Lfo::new(48000.0);
+/// lfo.set_frequency(2.0);
+///
+/// // 2 cycles per second, so 4 zero crossings
+/// let sine: Vec<f32> = (0..48000).map(|_| lfo.process()).collect();
+/// let crossings = sine.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count();
+/// assert_eq!(3, crossings); // the last crossing is on the next sample
+/// ```
+///
+/// [process]: Lfo::process
+/// [process_block]: Lfo::process_block
+#[derive(Clone, Copy, Debug)]
+pub struct Lfo {
+    phasor: Phasor,
+    shape: LfoShape,
+    last_phase: f32,
+    held: f32,
+    seed: u32,
+}
+
+impl Lfo {
+    /// Create a new sine LFO at 1 Hz
+    pub fn new(sample_rate: f32) -> Self {
+        let mut phasor = Phasor::new(sample_rate);
+        phasor.set_frequency(1.0);
+        let mut lfo = Self {
+            phasor,
+            shape: LfoShape::Sine,
+            last_phase: 0.0,
+            held: 0.0,
+            seed: 0x1234_5678,
+        };
+        lfo.held = lfo.random();
+        lfo
+    }
+
+    /// Set the frequency in Hz
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.phasor.set_frequency(frequency);
+    }
+
+    /// Frequency in Hz
+    pub fn frequency(&self) -> f32 {
+        self.phasor.frequency()
+    }
+
+    /// Change the sample rate, keeping the same frequency
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.phasor.set_sample_rate(sample_rate);
+    }
+
+    /// Set the waveform shape
+    pub fn set_shape(&mut self, shape: LfoShape) {
+        self.shape = shape;
+    }
+
+    /// Waveform shape
+    pub fn shape(&self) -> LfoShape {
+        self.shape
+    }
+
+    /// Restart the cycle, e.g. to sync to a note on or tap tempo
+    pub fn reset(&mut self) {
+        self.phasor.set_phase(0.0);
+        self.last_phase = 0.0;
+        self.held = self.random();
+    }
+
+    /// Return the current value, then advance by one sample
+    ///
+    /// ```
+    /// # use owl_patch::oscillator::{Lfo, LfoShape};
+    /// let mut lfo = Lfo::new(8.0);
+    ///
+    /// lfo.set_shape(LfoShape::Triangle);
+    /// let triangle: Vec<f32> = (0..8).map(|_| lfo.process()).collect();
+    /// assert_eq!(vec![0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -0.5], triangle);
+    ///
+    /// lfo.set_shape(LfoShape::Ramp);
+    /// let ramp: Vec<f32> = (0..8).map(|_| lfo.process()).collect();
+    /// assert_eq!(vec![-1.0, -0.75, -0.5, -0.25, 0.0, 0.25, 0.5, 0.75], ramp);
+    ///
+    /// lfo.set_shape(LfoShape::Sine);
+    /// let sine: Vec<f32> = (0..8).map(|_| lfo.process()).collect();
+    /// assert!((sine[2] - 1.0).abs() < 1e-3);
+    /// assert!((sine[6] + 1.0).abs() < 1e-3);
+    /// ```
+    pub fn process(&mut self) -> f32 {
+        let phase = self.phasor.next();
+
+        // Any jump of more than half a cycle is a wrap, in either direction
+        if (phase - self.last_phase).abs() > 0.5 {
+            self.held = self.random();
+        }
+        self.last_phase = phase;
+
+        match self.shape {
+            LfoShape::Sine => {
+                let x = phase * core::f32::consts::TAU;
+
+                #[cfg(feature = "fastmaths")]
+                return x.fast_sin();
+
+                #[cfg(not(feature = "fastmaths"))]
+                return x.sin();
+            }
+            LfoShape::Triangle => {
+                let shifted = phase + 0.75;
+                4.0 * (shifted - shifted.floor() - 0.5).abs() - 1.0
+            }
+            LfoShape::Ramp => 2.0 * phase - 1.0,
+            LfoShape::SampleAndHold => self.held,
+        }
+    }
+
+    /// Fill a buffer with successive values
+    ///
+    /// ```
+    /// # use owl_patch::oscillator::{Lfo, LfoShape};
+    /// # use owl_patch::sample_buffer::{Buffer, Mono};
+    /// let mut lfo = Lfo::new(1000.0);
+    /// lfo.set_frequency(10.0);
+    /// lfo.set_shape(LfoShape::SampleAndHold);
+    ///
+    /// let mut buffer: Buffer<Mono, Box<[f32]>> = Buffer::new(1, 300);
+    /// lfo.process_block(&mut buffer);
+    ///
+    /// // Held for each 100 sample cycle, so it changes twice
+    /// assert!(buffer.iter().all(|s| (-1.0..1.0).contains(s)));
+    /// assert_eq!(2, buffer.windows(2).filter(|w| w[0] != w[1]).count());
+    /// assert!(buffer[..99].iter().all(|s| *s == buffer[0]));
+    /// ```
+    pub fn process_block<C: MutableContainer<Item = f32>>(&mut self, buffer: &mut Buffer<Mono, C>) {
+        buffer.iter_mut().for_each(|s| *s = self.process());
+    }
+
+    /// Next value from a xorshift generator, from -1.0 to 1.0
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1 << 23) as f32 - 1.0
+    }
+}
+
+/// Polynomial band-limited step correction, for a discontinuity at phase 0.0
+fn poly_blep(phase: f32, dt: f32) -> f32 {
+    if dt <= 0.0 {
+        0.0
+    } else if phase < dt {
+        let t = phase / dt;
+        t + t - t * t - 1.0
+    } else if phase > 1.0 - dt {
+        let t = (phase - 1.0) / dt;
+        t * t + t + t + 1.0
+    } else {
+        0.0
+    }
+}
@@ -1,14 +1,38 @@
-use core::slice;
+use core::{
+    slice,
+    sync::atomic::{AtomicU16, AtomicU32, Ordering},
+};
 
 use alloc::boxed::Box;
 
-use crate::sample_buffer::{Buffer, ConvertFrom, Interleaved};
+use crate::sample_buffer::{Buffer, ConvertFrom, ConvertFromClamped, Interleaved};
 
 use super::{
     AUDIO_FORMAT_24B16, AUDIO_FORMAT_24B32, AUDIO_FORMAT_CHANNEL_MASK, AUDIO_FORMAT_FORMAT_MASK,
 };
 
 /// Current audio settings (set by the os / device)
+///
+/// The channel count comes from the audio format reported by the device; buffers passed to the
+/// audio closures always hold this many channels.
+///
+/// ```
+/// # use owl_patch::test_harness;
+/// for channels in [4, 8] {
+///     let mut pv = unsafe { test_harness::program_vector_with_channels(channels) };
+///     let settings = pv.audio().settings;
+///     assert_eq!(channels as usize, settings.channels);
+///     assert_eq!(32, settings.blocksize);
+///
+///     let input = vec![0.5; settings.channels * settings.blocksize];
+///     let output = test_harness::process_blocks(pv.audio(), &input, |input, output| {
+///         assert_eq!(settings.channels, input.frames().next().unwrap().len());
+///         output.samples_mut().copy_from_slice(input.samples());
+///     });
+///     assert_eq!(input.len(), output.len());
+///     assert_eq!(input.len(), test_harness::audio_output().len());
+/// }
+/// ```
 #[derive(Clone, Copy)]
 pub struct AudioSettings {
     /// sample rate in Hz
@@ -21,6 +45,55 @@ pub struct AudioSettings {
     pub format: AudioFormat,
 }
 
+impl AudioSettings {
+    /// The channel layout, see [ChannelLayout]
+    pub fn channel_layout(&self) -> ChannelLayout {
+        ChannelLayout::from_channels(self.channels)
+    }
+}
+
+/// Audio channel layout, for matching on the channel count
+///
+/// Most devices are stereo, but some have 4 or 8 channels. On these, the first two channels are
+/// usually the main left / right pair, so stereo processing of the first two channels still works, but
+/// any further channels will be ignored unless the patch handles [ChannelLayout::Multi] explicitly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelLayout {
+    /// Single channel; there is no right channel
+    Mono,
+    /// Left and right channels
+    Stereo,
+    /// More than 2 channels
+    Multi(usize),
+}
+
+impl ChannelLayout {
+    /// Layout for a channel count
+    ///
+    /// ```
+    /// # use owl_patch::program_vector::ChannelLayout;
+    /// assert_eq!(ChannelLayout::Mono, ChannelLayout::from_channels(1));
+    /// assert_eq!(ChannelLayout::Stereo, ChannelLayout::from_channels(2));
+    /// assert_eq!(ChannelLayout::Multi(4), ChannelLayout::from_channels(4));
+    /// ```
+    pub fn from_channels(channels: usize) -> Self {
+        match channels {
+            0 | 1 => Self::Mono,
+            2 => Self::Stereo,
+            n => Self::Multi(n),
+        }
+    }
+
+    /// Number of channels
+    pub fn channels(self) -> usize {
+        match self {
+            Self::Mono => 1,
+            Self::Stereo => 2,
+            Self::Multi(n) => n,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum AudioFormat {
     Format24B16,
@@ -84,6 +157,8 @@ impl ConvertFrom<Samplei32> for i32 {
 pub struct AudioBuffers {
     input: &'static *mut i32,
     output: &'static *mut i32,
+    sample_rate: &'static AtomicU32,
+    blocksize: &'static AtomicU16,
     /// Current audio settings (set by the os / device)
     pub settings: AudioSettings,
     program_ready: Option<unsafe extern "C" fn()>,
@@ -95,7 +170,8 @@ impl AudioBuffers {
     pub(crate) fn new(
         input: &'static *mut i32,
         output: &'static *mut i32,
-
+        sample_rate: &'static AtomicU32,
+        blocksize: &'static AtomicU16,
         settings: AudioSettings,
         program_ready: Option<unsafe extern "C" fn()>,
     ) -> Self {
@@ -105,6 +181,8 @@ impl AudioBuffers {
         Self {
             input,
             output,
+            sample_rate,
+            blocksize,
             settings,
             program_ready,
             input_buffer,
@@ -112,6 +190,29 @@ impl AudioBuffers {
         }
     }
 
+    /// The channel layout of the buffers passed to [run]
+    ///
+    /// ```
+    /// # use owl_patch::program_vector::ChannelLayout;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// match pv.audio().channel_layout() {
+    ///     ChannelLayout::Mono => { /* mono processing */ }
+    ///     ChannelLayout::Stereo => { /* stereo processing */ }
+    ///     ChannelLayout::Multi(n) => { /* use the first 2 of n channels */ }
+    /// }
+    /// # assert!(pv.audio().is_stereo());
+    /// ```
+    ///
+    /// [run]: AudioBuffers::run
+    pub fn channel_layout(&self) -> ChannelLayout {
+        self.settings.channel_layout()
+    }
+
+    /// True if there are exactly 2 channels
+    pub fn is_stereo(&self) -> bool {
+        self.channel_layout() == ChannelLayout::Stereo
+    }
+
     /// Start processing audio samples
     ///
     /// Supply a closure which will be run for each audio block as it is received.  The closure will have access to
@@ -123,17 +224,211 @@ impl AudioBuffers {
     /// [self.settings.channels]: AudioSettings
     pub fn run(
         &mut self,
-        f: impl FnMut(&Buffer<Interleaved, Box<[i32]>>, &mut Buffer<Interleaved, Box<[i32]>>),
+        mut f: impl FnMut(&Buffer<Interleaved, Box<[i32]>>, &mut Buffer<Interleaved, Box<[i32]>>),
+    ) -> ! {
+        self.run_with_settings(|_, input, output| f(input, output))
+    }
+
+    /// Start processing audio samples, with the current settings passed to the closure
+    ///
+    /// As [run], but if the sample rate or block size changes between blocks, [self.settings] is updated
+    /// (and the buffers reallocated) before the closure is called. Use this to re-initialise any DSP objects
+    /// which depend on the settings. Most devices never change these at runtime, but some do.
+    ///
+    /// ```
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// owl_patch::test_harness::run_blocks(2);
+    ///
+    /// let mut sample_rate = pv.audio().settings.sample_rate;
+    /// let mut block = 0;
+    /// pv.audio().run_with_settings(|settings, _input, output| {
+    ///     if settings.sample_rate != sample_rate {
+    ///         sample_rate = settings.sample_rate;
+    ///         // re-initialise filters etc.
+    ///     }
+    ///     assert_eq!(settings.blocksize * settings.channels, output.samples().len());
+    ///
+    ///     if block == 0 {
+    ///         owl_patch::test_harness::set_audio_settings(48000, 16);
+    ///     } else {
+    ///         assert_eq!(48000, sample_rate);
+    ///         assert_eq!(16, settings.blocksize);
+    ///     }
+    ///     block += 1;
+    /// });
+    /// ```
+    ///
+    /// This function never terminates.
+    ///
+    /// [run]: AudioBuffers::run
+    /// [self.settings]: AudioSettings
+    pub fn run_with_settings(
+        &mut self,
+        f: impl FnMut(
+            &AudioSettings,
+            &Buffer<Interleaved, Box<[i32]>>,
+            &mut Buffer<Interleaved, Box<[i32]>>,
+        ),
+    ) -> ! {
+        self.run_with_format(f, || {})
+    }
+
+    /// Start processing audio samples, with an idle closure for background work
+    ///
+    /// As [run], but `idle` is called after each block has been processed, while waiting for the next. Use it
+    /// for work which doesn't need to happen in sync with the audio, e.g. recalculating a lookup table.
+    ///
+    /// `idle` must not block; the next audio block can't be processed until it returns. Keep any single
+    /// call short, and spread long jobs over many calls.
+    ///
+    /// ```
+    /// # use core::cell::Cell;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # owl_patch::test_harness::run_blocks(3);
+    /// let blocks = Cell::new(0);
+    /// let idles = Cell::new(0);
+    ///
+    /// pv.audio().run_with_idle(
+    ///     |_input, _output| blocks.set(blocks.get() + 1),
+    ///     || {
+    ///         idles.set(idles.get() + 1);
+    ///         assert_eq!(blocks.get(), idles.get());
+    ///     },
+    /// );
+    /// ```
+    ///
+    /// This function never terminates.
+    ///
+    /// [run]: AudioBuffers::run
+    pub fn run_with_idle(
+        &mut self,
+        mut f: impl FnMut(&Buffer<Interleaved, Box<[i32]>>, &mut Buffer<Interleaved, Box<[i32]>>),
+        idle: impl FnMut(),
+    ) -> ! {
+        self.run_with_format(|_, input, output| f(input, output), idle)
+    }
+
+    /// Start processing audio samples, as floats
+    ///
+    /// As [run], but the samples are converted to f32 (-1.0 to 1.0) before the closure is called, and back again
+    /// afterwards, using buffers which are allocated once and reused. Output samples outside of -1.0 to 1.0 are
+    /// clamped, rather than wrapping around.
+    ///
+    /// ```
+    /// # use owl_patch::{sample_buffer::*, test_harness};
+    /// # let mut pv = unsafe { test_harness::program_vector() };
+    /// test_harness::set_audio_input(&[0x1234; 64]);
+    /// test_harness::run_blocks(2);
+    ///
+    /// let mut block = 0;
+    /// pv.audio().run_f32(|input, output| {
+    ///     // matches converting by hand
+    ///     let mut expected = 0.0f32;
+    ///     expected.convert_from(0x1234 << 16);
+    ///     assert!(input.samples().iter().all(|s| *s == expected));
+    ///
+    ///     if block == 1 {
+    ///         // and converts back to what went in
+    ///         assert_eq!(vec![0x1234; 64], test_harness::audio_output());
+    ///     }
+    ///     output.samples_mut().copy_from_slice(input.samples());
+    ///     block += 1;
+    /// });
+    /// ```
+    ///
+    /// This function never terminates.
+    ///
+    /// [run]: AudioBuffers::run
+    pub fn run_f32(
+        &mut self,
+        f: impl FnMut(&Buffer<Interleaved, Box<[f32]>>, &mut Buffer<Interleaved, Box<[f32]>>),
+    ) -> ! {
+        self.run_with_settings(f32_adapter(&self.settings, f))
+    }
+
+    /// Start processing audio samples, one frame at a time
+    ///
+    /// As [run_f32], but the closure is called once for every frame, with a slice holding one f32 sample for
+    /// each channel of input, and one to write each channel of output to. Useful for simple patches which
+    /// don't need to know about blocks.
+    ///
+    /// ```
+    /// # use owl_patch::{sample_buffer::*, test_harness};
+    /// # let mut pv = unsafe { test_harness::program_vector() };
+    /// test_harness::set_audio_input(&[0x1234; 64]);
+    /// test_harness::run_blocks(2);
+    ///
+    /// // The same gain, applied to a whole block
+    /// let mut block: Buffer<Interleaved, Box<[f32]>> = Buffer::new(2, 32);
+    /// block.convert_from(&[0x1234 << 16; 64][..]);
+    /// block.samples_mut().iter_mut().for_each(|s| *s *= 0.5);
+    /// let mut expected = [0i32; 64];
+    /// expected.as_mut_slice().convert_from(&block);
+    ///
+    /// let mut frames = 0;
+    /// pv.audio().run_per_sample(|input, output| {
+    ///     if frames == 32 {
+    ///         let expected: Vec<i32> = expected.iter().map(|s| s >> 16).collect();
+    ///         assert_eq!(expected, test_harness::audio_output());
+    ///     }
+    ///     for (o, i) in output.iter_mut().zip(input) {
+    ///         *o = i * 0.5;
+    ///     }
+    ///     frames += 1;
+    /// });
+    /// ```
+    ///
+    /// This function never terminates.
+    ///
+    /// [run_f32]: AudioBuffers::run_f32
+    pub fn run_per_sample(&mut self, mut f: impl FnMut(&[f32], &mut [f32])) -> ! {
+        self.run_f32(|input, output| {
+            for (i, o) in input.frames().zip(output.frames_mut()) {
+                f(i, o);
+            }
+        })
+    }
+
+    /// Pick up any change to the sample rate or block size made by the OS
+    fn update_settings(&mut self) {
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed) as usize;
+        let blocksize = self.blocksize.load(Ordering::Relaxed) as usize;
+
+        if sample_rate == self.settings.sample_rate && blocksize == self.settings.blocksize {
+            return;
+        }
+
+        self.settings.sample_rate = sample_rate;
+        if blocksize != self.settings.blocksize {
+            self.settings.blocksize = blocksize;
+            self.input_buffer = Buffer::new(self.settings.channels, blocksize);
+            self.output_buffer = Buffer::new(self.settings.channels, blocksize);
+        }
+    }
+
+    fn run_with_format(
+        &mut self,
+        f: impl FnMut(
+            &AudioSettings,
+            &Buffer<Interleaved, Box<[i32]>>,
+            &mut Buffer<Interleaved, Box<[i32]>>,
+        ),
+        idle: impl FnMut(),
     ) -> ! {
         match self.settings.format {
-            AudioFormat::Format24B16 => self.run_with_format::<Samplew16>(f),
-            AudioFormat::Format24B32 => self.run_with_format::<Samplei32>(f),
+            AudioFormat::Format24B16 => self.run_with_sample_type::<Samplew16>(f, idle),
+            AudioFormat::Format24B32 => self.run_with_sample_type::<Samplei32>(f, idle),
         }
     }
 
-    fn run_with_format<F>(
+    fn run_with_sample_type<F>(
         &mut self,
-        mut f: impl FnMut(&Buffer<Interleaved, Box<[i32]>>, &mut Buffer<Interleaved, Box<[i32]>>),
+        mut f: impl FnMut(
+            &AudioSettings,
+            &Buffer<Interleaved, Box<[i32]>>,
+            &mut Buffer<Interleaved, Box<[i32]>>,
+        ),
+        mut idle: impl FnMut(),
     ) -> !
     where
         i32: ConvertFrom<F>,
@@ -148,28 +443,88 @@ impl AudioBuffers {
             // Note: any callbacks are invoked during this call
             unsafe { program_ready() };
 
-            // Safety: The OS provides a valid buffer of the appropriate length.
-            // The buffers remain valid until the next call to program_ready()
-            let input = unsafe {
-                slice::from_raw_parts(
-                    (*self.input) as *const F,
-                    self.settings.blocksize * self.settings.channels,
-                )
-            };
+            self.process_block_with_sample_type::<F>(&mut f);
+
+            idle();
+        }
+    }
+
+    /// Process a single block, without waiting for the OS. Lets the test harness drive the audio closure
+    #[cfg(not(target_os = "none"))]
+    pub(crate) fn process_block(
+        &mut self,
+        f: &mut impl FnMut(
+            &AudioSettings,
+            &Buffer<Interleaved, Box<[i32]>>,
+            &mut Buffer<Interleaved, Box<[i32]>>,
+        ),
+    ) {
+        match self.settings.format {
+            AudioFormat::Format24B16 => self.process_block_with_sample_type::<Samplew16>(f),
+            AudioFormat::Format24B32 => self.process_block_with_sample_type::<Samplei32>(f),
+        }
+    }
+
+    fn process_block_with_sample_type<F>(
+        &mut self,
+        f: &mut impl FnMut(
+            &AudioSettings,
+            &Buffer<Interleaved, Box<[i32]>>,
+            &mut Buffer<Interleaved, Box<[i32]>>,
+        ),
+    ) where
+        i32: ConvertFrom<F>,
+        F: ConvertFrom<i32> + Copy,
+    {
+        self.update_settings();
+
+        // Safety: The OS provides a valid buffer of the appropriate length.
+        // The buffers remain valid until the next call to program_ready()
+        let input = unsafe {
+            slice::from_raw_parts(
+                (*self.input) as *const F,
+                self.settings.blocksize * self.settings.channels,
+            )
+        };
+
+        self.input_buffer.convert_from(input);
+
+        f(&self.settings, &self.input_buffer, &mut self.output_buffer);
 
-            self.input_buffer.convert_from(input);
+        // Safety: The OS provides a valid buffer of the appropriate length
+        let mut output = unsafe {
+            slice::from_raw_parts_mut(
+                (*self.output) as *mut F,
+                self.settings.blocksize * self.settings.channels,
+            )
+        };
+
+        output.convert_from(&self.output_buffer);
+    }
+}
+
+/// Wrap a closure over f32 buffers, so that it can be run over the native i32 buffers. The f32 buffers are
+/// allocated once, and only reallocated if the block size changes.
+#[allow(clippy::type_complexity)]
+pub(crate) fn f32_adapter(
+    settings: &AudioSettings,
+    mut f: impl FnMut(&Buffer<Interleaved, Box<[f32]>>, &mut Buffer<Interleaved, Box<[f32]>>),
+) -> impl FnMut(&AudioSettings, &Buffer<Interleaved, Box<[i32]>>, &mut Buffer<Interleaved, Box<[i32]>>)
+{
+    let mut input_f32 = Buffer::<Interleaved, _>::new(settings.channels, settings.blocksize);
+    let mut output_f32 = Buffer::<Interleaved, _>::new(settings.channels, settings.blocksize);
 
-            f(&self.input_buffer, &mut self.output_buffer);
+    move |settings, input, output| {
+        if input_f32.samples().len() != input.samples().len() {
+            input_f32 = Buffer::new(settings.channels, settings.blocksize);
+            output_f32 = Buffer::new(settings.channels, settings.blocksize);
+        }
 
-            // Safety: The OS provides a valid buffer of the appropriate length
-            let mut output = unsafe {
-                slice::from_raw_parts_mut(
-                    (*self.output) as *mut F,
-                    self.settings.blocksize * self.settings.channels,
-                )
-            };
+        input_f32.convert_from(input);
+        f(&input_f32, &mut output_f32);
 
-            output.convert_from(&self.output_buffer);
+        for (o, i) in output.samples_mut().iter_mut().zip(output_f32.samples()) {
+            o.convert_from_clamped(*i);
         }
     }
 }
@@ -0,0 +1,198 @@
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cmp::Ordering,
+    ptr::{null_mut, NonNull},
+};
+
+use talc::*;
+
+use crate::ffi::program_vector::MemorySegment;
+
+impl From<&MemorySegment> for talc::Span {
+    fn from(segment: &MemorySegment) -> talc::Span {
+        talc::Span::from_base_size(segment.location, segment.size as usize)
+    }
+}
+
+struct State {
+    talc: Talc<ErrOnOom>,
+    peak: usize,
+}
+
+impl State {
+    fn update_peak(&mut self) {
+        self.peak = self.peak.max(self.talc.get_counters().allocated_bytes);
+    }
+}
+
+/// Talc allocator which also records the peak number of bytes allocated
+///
+/// The peak is updated while still holding the lock taken for the allocation, so it can't miss a concurrent
+/// allocation.
+pub(crate) struct Heap {
+    state: spin::Mutex<State>,
+}
+
+impl Heap {
+    pub const fn new() -> Self {
+        Self {
+            state: spin::Mutex::new(State {
+                talc: Talc::new(ErrOnOom),
+                peak: 0,
+            }),
+        }
+    }
+
+    /// Add a region of memory to the heap
+    ///
+    /// # Safety
+    /// The memory must be valid for reads and writes, not used for anything else while the heap is in use, and
+    /// not overlap with any other claimed region
+    // Only the device claims memory outside of tests, the host harness has no heap segments
+    #[cfg_attr(not(target_os = "none"), allow(dead_code))]
+    pub unsafe fn claim(&self, span: Span) {
+        let _ = self.state.lock().talc.claim(span);
+    }
+
+    /// Bytes allocated at present
+    pub fn bytes_used(&self) -> usize {
+        self.state.lock().talc.get_counters().allocated_bytes
+    }
+
+    /// The most bytes allocated at any one time since the heap was created
+    pub fn peak_bytes_used(&self) -> usize {
+        self.state.lock().peak
+    }
+
+    /// Bytes still available for allocation in the claimed memory
+    pub fn free_bytes(&self) -> usize {
+        self.state.lock().talc.get_counters().available_bytes
+    }
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut state = self.state.lock();
+        let ptr = state
+            .talc
+            .malloc(layout)
+            .map_or(null_mut(), NonNull::as_ptr);
+        state.update_peak();
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.state
+            .lock()
+            .talc
+            .free(NonNull::new_unchecked(ptr), layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let nn_ptr = NonNull::new_unchecked(ptr);
+        let mut state = self.state.lock();
+
+        match new_size.cmp(&layout.size()) {
+            Ordering::Greater => {
+                let ptr = state
+                    .talc
+                    .grow(nn_ptr, layout, new_size)
+                    .map_or(null_mut(), NonNull::as_ptr);
+                state.update_peak();
+                ptr
+            }
+            Ordering::Less => {
+                state.talc.shrink(nn_ptr, layout, new_size);
+                ptr
+            }
+            Ordering::Equal => ptr,
+        }
+    }
+}
+
+/// The heap used by the global allocator. On the host it exists but is never used for allocation, so the
+/// heap functions always return 0 there
+#[cfg_attr(target_os = "none", global_allocator)]
+pub(crate) static ALLOCATOR: Heap = Heap::new();
+
+/// get total bytes allocated at present
+pub fn heap_bytes_used() -> usize {
+    ALLOCATOR.bytes_used()
+}
+
+/// get the most bytes allocated at any one time since the patch started
+pub fn heap_peak_bytes_used() -> usize {
+    ALLOCATOR.peak_bytes_used()
+}
+
+/// get bytes still available for allocation in the claimed memory segments
+pub fn heap_free_bytes() -> usize {
+    ALLOCATOR.free_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(align(8))]
+    struct Memory([u8; 4096]);
+
+    fn layout(size: usize) -> Layout {
+        Layout::from_size_align(size, 4).unwrap()
+    }
+
+    #[test]
+    fn test_peak_bytes_used() {
+        let mut memory = Memory([0; 4096]);
+        let heap = Heap::new();
+        unsafe { heap.claim(Span::from_array(&mut memory.0 as *mut [u8; 4096])) };
+
+        let base = heap.peak_bytes_used();
+        let a = unsafe { heap.alloc(layout(256)) };
+        let b = unsafe { heap.alloc(layout(512)) };
+        assert!(!a.is_null() && !b.is_null());
+        let peak = heap.peak_bytes_used();
+        assert!(peak >= base + 768);
+
+        assert_eq!(peak, heap.bytes_used());
+
+        // Freeing doesn't lower the peak
+        unsafe { heap.dealloc(b, layout(512)) };
+        assert_eq!(peak, heap.peak_bytes_used());
+        assert!(heap.peak_bytes_used() > heap.bytes_used());
+
+        // Neither does allocating less than has already been freed
+        let c = unsafe { heap.alloc(layout(128)) };
+        assert_eq!(peak, heap.peak_bytes_used());
+
+        // Growing past the old high-water mark raises it
+        let c = unsafe { heap.realloc(c, layout(128), 1024) };
+        assert!(!c.is_null());
+        assert!(heap.peak_bytes_used() >= base + 256 + 1024);
+
+        unsafe {
+            heap.dealloc(a, layout(256));
+            heap.dealloc(c, layout(1024));
+        }
+        assert!(heap.peak_bytes_used() >= base + 256 + 1024);
+        assert_eq!(base, heap.bytes_used());
+    }
+
+    #[test]
+    fn test_free_bytes() {
+        let mut memory = Memory([0; 4096]);
+        let heap = Heap::new();
+        assert_eq!(0, heap.free_bytes());
+
+        unsafe { heap.claim(Span::from_array(&mut memory.0 as *mut [u8; 4096])) };
+        let claimed = heap.free_bytes();
+        assert!(claimed > 0 && claimed <= 4096);
+
+        let ptr = unsafe { heap.alloc(layout(1000)) };
+        assert!(!ptr.is_null());
+        assert!(heap.free_bytes() <= claimed - 1000);
+
+        unsafe { heap.dealloc(ptr, layout(1000)) };
+        assert_eq!(claimed, heap.free_bytes());
+    }
+}
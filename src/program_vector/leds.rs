@@ -0,0 +1,67 @@
+use super::Parameters;
+use crate::PatchButtonId;
+
+/// LED colours available on OWL hardware
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedColour {
+    /// Off
+    Off,
+    /// Green
+    Green,
+    /// Red
+    Red,
+    /// Green and red together
+    Yellow,
+}
+
+/// Controls the device's status LED, obtained from [ProgramVector::leds]
+///
+/// OpenWare doesn't expose RGB LED control to patches. The two colour LED on the OWL Pedal (and similar) is
+/// driven by setting the [GREEN_BUTTON] and [RED_BUTTON] outputs, which this does for you. On devices without
+/// such an LED, nothing happens.
+///
+/// ```
+/// # use owl_patch::{program_vector::LedColour, PatchButtonId};
+/// # use owl_patch::test_harness::output_button;
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// let leds = pv.leds();
+///
+/// leds.set(LedColour::Red);
+/// assert!(output_button(PatchButtonId::RED_BUTTON));
+/// assert!(!output_button(PatchButtonId::GREEN_BUTTON));
+///
+/// leds.set(LedColour::Yellow);
+/// assert!(output_button(PatchButtonId::RED_BUTTON));
+/// assert!(output_button(PatchButtonId::GREEN_BUTTON));
+///
+/// leds.set(LedColour::Off);
+/// assert!(!output_button(PatchButtonId::RED_BUTTON));
+/// assert!(!output_button(PatchButtonId::GREEN_BUTTON));
+/// ```
+///
+/// [ProgramVector::leds]: super::ProgramVector::leds
+/// [GREEN_BUTTON]: PatchButtonId::GREEN_BUTTON
+/// [RED_BUTTON]: PatchButtonId::RED_BUTTON
+#[derive(Clone, Copy)]
+pub struct Leds {
+    parameters: Parameters,
+}
+
+impl Leds {
+    pub(crate) fn new(parameters: Parameters) -> Self {
+        Self { parameters }
+    }
+
+    /// Set the LED colour
+    pub fn set(&self, colour: LedColour) {
+        let (green, red) = match colour {
+            LedColour::Off => (false, false),
+            LedColour::Green => (true, false),
+            LedColour::Red => (false, true),
+            LedColour::Yellow => (true, true),
+        };
+        self.parameters
+            .set_button(PatchButtonId::GREEN_BUTTON, green);
+        self.parameters.set_button(PatchButtonId::RED_BUTTON, red);
+    }
+}
@@ -1,20 +1,21 @@
 use ::core::option::Option;
 use core::{
-    cell::RefCell,
+    cell::{RefCell, UnsafeCell},
     ffi::c_char,
-    sync::atomic::{compiler_fence, Ordering},
+    fmt::{self, Write},
+    sync::atomic::{compiler_fence, AtomicBool, AtomicPtr, Ordering},
 };
 
 use spin::Mutex;
 
 use crate::ffi::program_vector::ProgramVectorAudioStatus;
 
-use super::CONFIGURATION_ERROR_STATUS;
+use super::{ring::SpscRing, CONFIGURATION_ERROR_STATUS};
+
+type ProgramStatusFn = unsafe extern "C" fn(status: ProgramVectorAudioStatus);
 
 pub struct Messages {
-    message: &'static mut *mut c_char,
-    error: &'static mut i8,
-    program_status: Option<unsafe extern "C" fn(status: ProgramVectorAudioStatus)>,
+    message: *mut *mut c_char,
     buffer: [u8; 64],
 }
 
@@ -22,26 +23,49 @@ unsafe impl Send for Messages {}
 
 static INSTANCE: Mutex<RefCell<Option<Messages>>> = Mutex::new(RefCell::new(None));
 
+/// Everything [error] needs, kept outside of [INSTANCE] so that it can never be blocked by a lock held
+/// elsewhere (e.g. by a panic while formatting a debug message)
+struct ErrorTarget {
+    message: AtomicPtr<*mut c_char>,
+    error: AtomicPtr<i8>,
+    program_status: AtomicPtr<()>,
+    taken: AtomicBool,
+    buffer: UnsafeCell<[u8; 64]>,
+}
+
+// Safety: the buffer is only written by whichever caller first sets `taken`
+unsafe impl Sync for ErrorTarget {}
+
+static ERROR_TARGET: ErrorTarget = ErrorTarget {
+    message: AtomicPtr::new(core::ptr::null_mut()),
+    error: AtomicPtr::new(core::ptr::null_mut()),
+    program_status: AtomicPtr::new(core::ptr::null_mut()),
+    taken: AtomicBool::new(false),
+    buffer: UnsafeCell::new([0; 64]),
+};
+
 impl Messages {
     pub fn init(
         message: &'static mut *mut c_char,
         error: &'static mut i8,
-        program_status: Option<unsafe extern "C" fn(status: ProgramVectorAudioStatus)>,
+        program_status: Option<ProgramStatusFn>,
     ) {
-        INSTANCE
-            .lock()
-            .replace(Some(Self::new(message, error, program_status)));
+        let message: *mut *mut c_char = message;
+
+        ERROR_TARGET.message.store(message, Ordering::Relaxed);
+        ERROR_TARGET.error.store(error, Ordering::Relaxed);
+        ERROR_TARGET.program_status.store(
+            program_status.map_or(core::ptr::null_mut(), |f| f as *mut ()),
+            Ordering::Relaxed,
+        );
+        ERROR_TARGET.taken.store(false, Ordering::Release);
+
+        INSTANCE.lock().replace(Some(Self::new(message)));
     }
 
-    pub fn new(
-        message: &'static mut *mut c_char,
-        error: &'static mut i8,
-        program_status: Option<unsafe extern "C" fn(status: ProgramVectorAudioStatus)>,
-    ) -> Self {
+    pub fn new(message: *mut *mut c_char) -> Self {
         Self {
             message,
-            error,
-            program_status,
             buffer: [0u8; 64],
         }
     }
@@ -50,19 +74,20 @@ impl Messages {
         let len = message.len().min(self.buffer.len() - 1);
         self.buffer[..len].copy_from_slice(&message.as_bytes()[..len]);
         self.buffer[len] = 0;
-        *self.message = self.buffer.as_mut_ptr() as *mut c_char
+        // Safety: points into the program vector, which lives forever
+        unsafe { *self.message = self.buffer.as_mut_ptr() as *mut c_char }
     }
 
-    fn error(&mut self, message: &str) {
-        self.debug_message(message);
-        *self.error = CONFIGURATION_ERROR_STATUS;
-
-        if let Some(program_status) = self.program_status {
-            // This function never returns
-            unsafe {
-                program_status(ProgramVectorAudioStatus::AUDIO_ERROR_STATUS);
-            }
-        }
+    fn debug_message_args(&mut self, args: fmt::Arguments) {
+        let mut writer = TruncatingWriter {
+            buffer: &mut self.buffer,
+            len: 0,
+        };
+        let _ = writer.write_fmt(args);
+        let len = writer.len;
+        self.buffer[len] = 0;
+        // Safety: points into the program vector, which lives forever
+        unsafe { *self.message = self.buffer.as_mut_ptr() as *mut c_char }
     }
 }
 
@@ -73,13 +98,212 @@ pub fn debug_message(message: &str) {
     }
 }
 
+/// Publish a formatted debug message, without allocating. Messages will be truncated to 63 chars
+///
+/// Usually called via the [debug_fmt!] macro
+///
+/// [debug_fmt!]: crate::debug_fmt
+pub fn debug_message_args(args: fmt::Arguments) {
+    if let Some(instance) = INSTANCE.lock().get_mut() {
+        instance.debug_message_args(args)
+    }
+}
+
+/// Publish a formatted debug message, without allocating. Takes the same arguments as `format!`
+///
+/// ```
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// let cycles = 1234;
+/// owl_patch::debug_fmt!("cycles: {}", cycles);
+/// # assert_eq!(Some("cycles: 1234".to_string()), owl_patch::test_harness::debug_message());
+/// ```
+///
+/// Messages longer than 63 chars are truncated
+/// ```
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// owl_patch::debug_fmt!("{:>70}", 42);
+/// # let message = owl_patch::test_harness::debug_message().unwrap();
+/// assert_eq!(63, message.len());
+/// assert!(message.ends_with(" "));
+/// ```
+#[macro_export]
+macro_rules! debug_fmt {
+    ($($arg:tt)*) => {
+        $crate::program_vector::debug_message_args(::core::format_args!($($arg)*))
+    };
+}
+
+/// Writes into a fixed buffer, silently dropping anything which doesn't fit (leaving space for a terminator)
+struct TruncatingWriter<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for TruncatingWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let available = self.buffer.len() - 1 - self.len;
+        let count = s.len().min(available);
+        self.buffer[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        Ok(())
+    }
+}
+
+/// Maximum length of a message held in a [DebugLog]
+const LOG_MESSAGE_LEN: usize = 63;
+
+#[derive(Clone, Copy)]
+struct LogEntry {
+    bytes: [u8; LOG_MESSAGE_LEN + 1],
+    len: usize,
+}
+
+/// Fixed size, lock-free log of debug messages, for logging from the audio closure
+///
+/// Writing with [debug_message] from inside the audio loop competes with the rest of the patch for the
+/// shared message buffer. Instead, [push] messages here and pass them on to the OS between blocks with
+/// [publish_next] or [drain]. It is single-producer / single-consumer: only one part of the patch should push,
+/// and only one should read. Messages are truncated to 63 chars.
+///
+/// ```
+/// # use owl_patch::program_vector::DebugLog;
+/// static LOG: DebugLog<4> = DebugLog::new();
+///
+/// // audio side
+/// LOG.push("first");
+/// LOG.push_args(format_args!("block {}", 2));
+///
+/// // other side
+/// let mut messages = Vec::new();
+/// LOG.drain(|message| messages.push(message.to_string()));
+/// assert_eq!(vec!["first", "block 2"], messages);
+/// ```
+///
+/// [push]: DebugLog::push
+/// [publish_next]: DebugLog::publish_next
+/// [drain]: DebugLog::drain
+pub struct DebugLog<const N: usize> {
+    ring: SpscRing<LogEntry, N>,
+}
+
+impl<const N: usize> Default for DebugLog<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> DebugLog<N> {
+    /// Create an empty log, holding up to N messages. N must be at least 1
+    pub const fn new() -> Self {
+        Self {
+            ring: SpscRing::new(LogEntry {
+                bytes: [0; LOG_MESSAGE_LEN + 1],
+                len: 0,
+            }),
+        }
+    }
+
+    /// Add a message. Returns false (dropping the message) if the log is full
+    pub fn push(&self, message: &str) -> bool {
+        self.push_args(format_args!("{}", message))
+    }
+
+    /// Add a formatted message, without allocating. Returns false (dropping the message) if the log is full
+    pub fn push_args(&self, args: fmt::Arguments) -> bool {
+        self.ring.push_with(|entry| {
+            let mut writer = TruncatingWriter {
+                buffer: &mut entry.bytes,
+                len: 0,
+            };
+            let _ = writer.write_fmt(args);
+            entry.len = writer.len;
+        })
+    }
+
+    /// Pass the oldest message to `f` and remove it. Returns false if the log was empty
+    pub fn pop(&self, f: impl FnOnce(&str)) -> bool {
+        self.ring
+            .pop_with(|entry| f(str_from_truncated(&entry.bytes[..entry.len])))
+            .is_some()
+    }
+
+    /// Pass every message to `f`, oldest first, emptying the log
+    pub fn drain(&self, mut f: impl FnMut(&str)) {
+        while self.pop(&mut f) {}
+    }
+
+    /// Publish the oldest message with [debug_message]. The OS only shows the latest message, so calling
+    /// this once per block gives each message a chance to be seen. Returns false if the log was empty
+    pub fn publish_next(&self) -> bool {
+        self.pop(debug_message)
+    }
+}
+
+/// Truncation may have split a multi-byte char, drop any partial char from the end
+fn str_from_truncated(bytes: &[u8]) -> &str {
+    match core::str::from_utf8(bytes) {
+        Ok(s) => s,
+        // Safety: valid_up_to marks the end of the valid utf8
+        Err(e) => unsafe { core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) },
+    }
+}
+
 /// Publish an unrecoverable error - will not return
+///
+/// The message is written to its own static buffer, without taking the lock used by [debug_message], so it
+/// is always published; even if the error is raised while a debug message is being written. The message is
+/// in place before the OS is told about the error. Only the first error is published, any later calls just
+/// halt.
+///
+/// ```
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// # owl_patch::test_harness::expect_error("formatting failed");
+/// struct Broken;
+///
+/// impl core::fmt::Display for Broken {
+///     fn fmt(&self, _f: &mut core::fmt::Formatter) -> core::fmt::Result {
+///         owl_patch::program_vector::error("formatting failed")
+///     }
+/// }
+///
+/// owl_patch::debug_fmt!("{}", Broken);
+/// # unreachable!();
+/// ```
 pub fn error(message: &str) -> ! {
-    if let Some(instance) = INSTANCE.lock().get_mut() {
-        instance.error(message);
-    } // else { ¯\(ツ)/¯ }
+    if !ERROR_TARGET.taken.swap(true, Ordering::Acquire) {
+        publish_error(message);
+    }
 
     loop {
         compiler_fence(Ordering::SeqCst);
     }
 }
+
+fn publish_error(message: &str) {
+    let message_ptr = ERROR_TARGET.message.load(Ordering::Relaxed);
+    let error_ptr = ERROR_TARGET.error.load(Ordering::Relaxed);
+    if message_ptr.is_null() || error_ptr.is_null() {
+        return; // ¯\_(ツ)_/¯
+    }
+
+    // Safety: only reachable once, by whoever set `taken`
+    let buffer = unsafe { &mut *ERROR_TARGET.buffer.get() };
+    let len = message.len().min(buffer.len() - 1);
+    buffer[..len].copy_from_slice(&message.as_bytes()[..len]);
+    buffer[len] = 0;
+
+    // Safety: both point into the program vector, which lives forever
+    unsafe {
+        *message_ptr = buffer.as_mut_ptr() as *mut c_char;
+        *error_ptr = CONFIGURATION_ERROR_STATUS;
+    }
+    compiler_fence(Ordering::SeqCst);
+
+    let program_status = ERROR_TARGET.program_status.load(Ordering::Relaxed);
+    if !program_status.is_null() {
+        // Safety: stored from a `ProgramStatusFn` in `init`
+        let program_status: ProgramStatusFn = unsafe { core::mem::transmute(program_status) };
+        // This function never returns
+        unsafe { program_status(ProgramVectorAudioStatus::AUDIO_ERROR_STATUS) };
+    }
+}
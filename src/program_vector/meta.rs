@@ -1,4 +1,4 @@
-use core::slice;
+use core::{ffi::c_char, slice};
 
 use crate::ffi::program_vector as ffi;
 pub use ffi::MemorySegment;
@@ -31,6 +31,72 @@ pub const PRISM_HARDWARE: u8 = ffi::PRISM_HARDWARE as u8;
 /// Player hardware identifier
 pub const PLAYER_HARDWARE: u8 = ffi::PLAYER_HARDWARE as u8;
 
+/// Core clock speed in MHz for a hardware version, if known
+///
+/// ```
+/// # use owl_patch::program_vector::*;
+/// assert_eq!(Some(168), hardware_core_clock(OWL_PEDAL_HARDWARE));
+/// assert_eq!(Some(168), hardware_core_clock(OWL_MODULAR_HARDWARE));
+/// assert_eq!(None, hardware_core_clock(0));
+/// ```
+pub fn hardware_core_clock(hardware_version: u8) -> Option<u32> {
+    match hardware_version {
+        OWL_PEDAL_HARDWARE | OWL_MODULAR_HARDWARE | OWL_RACK_HARDWARE | PRISM_HARDWARE
+        | PLAYER_HARDWARE => Some(168),
+        _ => None,
+    }
+}
+
+/// Features of the hardware the patch is running on, derived from the hardware version
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Hardware name
+    pub name: &'static str,
+    /// Whether the device has a screen
+    pub has_screen: bool,
+    /// Typical number of audio channels. The actual count is in [AudioSettings]
+    ///
+    /// [AudioSettings]: crate::program_vector::AudioSettings
+    pub channel_count_hint: usize,
+    /// Core clock speed in MHz, if known
+    pub core_clock_mhz: Option<u32>,
+}
+
+impl Capabilities {
+    /// Look up the capabilities for a hardware version. Unknown versions get conservative defaults.
+    ///
+    /// ```
+    /// # use owl_patch::program_vector::*;
+    /// let pedal = Capabilities::from_hardware_version(OWL_PEDAL_HARDWARE);
+    /// let prism = Capabilities::from_hardware_version(PRISM_HARDWARE);
+    ///
+    /// assert_eq!("OWL Pedal", pedal.name);
+    /// assert!(!pedal.has_screen);
+    /// assert!(prism.has_screen);
+    /// assert_ne!(pedal, Capabilities::from_hardware_version(OWL_MODULAR_HARDWARE));
+    ///
+    /// let unknown = Capabilities::from_hardware_version(0);
+    /// assert_eq!(None, unknown.core_clock_mhz);
+    /// ```
+    pub fn from_hardware_version(hardware_version: u8) -> Self {
+        let (name, has_screen) = match hardware_version {
+            OWL_PEDAL_HARDWARE => ("OWL Pedal", false),
+            OWL_MODULAR_HARDWARE => ("OWL Modular", false),
+            OWL_RACK_HARDWARE => ("OWL Rack", false),
+            PRISM_HARDWARE => ("Prism", true),
+            PLAYER_HARDWARE => ("Player", true),
+            _ => ("Unknown", false),
+        };
+
+        Self {
+            name,
+            has_screen,
+            channel_count_hint: 2,
+            core_clock_mhz: hardware_core_clock(hardware_version),
+        }
+    }
+}
+
 /// Program Metadata
 pub struct Meta {
     cycles_per_block: &'static u32,
@@ -38,6 +104,9 @@ pub struct Meta {
     checksum: ProgramVectorChecksum,
     hardware_version: u8,
     heap_locations: *mut MemorySegment,
+    patch_name: *const c_char,
+    register_patch: Option<unsafe extern "C" fn(name: *const c_char, inputs: u8, outputs: u8)>,
+    latency_samples: usize,
 }
 
 impl Meta {
@@ -47,6 +116,8 @@ impl Meta {
         checksum: ProgramVectorChecksum,
         hardware_version: u8,
         heap_locations: *mut MemorySegment,
+        patch_name: *const c_char,
+        register_patch: Option<unsafe extern "C" fn(name: *const c_char, inputs: u8, outputs: u8)>,
     ) -> Self {
         Self {
             cycles_per_block,
@@ -54,6 +125,31 @@ impl Meta {
             checksum,
             hardware_version,
             heap_locations,
+            patch_name,
+            register_patch,
+            latency_samples: 0,
+        }
+    }
+
+    /// Register the patch with the OS, declaring how many input and output channels it uses
+    ///
+    /// The patch is registered as stereo in / stereo out on startup; call this to declare different
+    /// routing, e.g. mono in, stereo out. This only affects what the host displays, the number of
+    /// channels in the audio buffers is still set by the device, see [AudioSettings].
+    ///
+    /// ```
+    /// # use owl_patch::test_harness;
+    /// let mut pv = unsafe { test_harness::program_vector() };
+    /// # assert_eq!(Some(("test".into(), 2, 2)), test_harness::registered_patch());
+    /// pv.meta().register_patch_io(1, 2);
+    /// # assert_eq!(Some(("test".into(), 1, 2)), test_harness::registered_patch());
+    /// ```
+    ///
+    /// [AudioSettings]: crate::program_vector::AudioSettings
+    pub fn register_patch_io(&mut self, inputs: u8, outputs: u8) {
+        if let Some(register_patch) = self.register_patch {
+            // Safety: patch_name is checked by the caller of ProgramVector::new
+            unsafe { register_patch(self.patch_name, inputs, outputs) };
         }
     }
 
@@ -67,6 +163,61 @@ impl Meta {
         *self.cycles_per_block
     }
 
+    /// Percentage of the available cycles per block being used, or None if the core clock of the hardware
+    /// is not known. Values approaching 100 risk audio dropouts.
+    ///
+    /// ```
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// let settings = pv.audio().settings;
+    /// if let Some(load) = pv.meta().cpu_load_percent(settings.sample_rate, settings.blocksize) {
+    ///     // display load
+    /// }
+    /// # assert!(pv.meta().cpu_load_percent(48000, 32).is_none());
+    /// ```
+    ///
+    /// On a known device, the load is relative to its core clock
+    /// ```
+    /// # use owl_patch::{program_vector::OWL_MODULAR_HARDWARE, test_harness};
+    /// test_harness::set_hardware_version(OWL_MODULAR_HARDWARE);
+    /// let mut pv = unsafe { test_harness::program_vector() };
+    ///
+    /// // 168MHz, 32 samples at 48kHz: 112000 cycles available per block
+    /// test_harness::set_cycles_per_block(56_000);
+    /// assert_eq!(Some(50.0), pv.meta().cpu_load_percent(48000, 32));
+    ///
+    /// test_harness::set_cycles_per_block(112_000);
+    /// assert_eq!(Some(100.0), pv.meta().cpu_load_percent(48000, 32));
+    ///
+    /// // Larger blocks have more cycles available
+    /// assert_eq!(Some(50.0), pv.meta().cpu_load_percent(48000, 64));
+    /// ```
+    pub fn cpu_load_percent(&self, sample_rate: usize, blocksize: usize) -> Option<f32> {
+        let clock_hz = hardware_core_clock(self.hardware_version)? as f32 * 1_000_000.0;
+        let available = clock_hz * blocksize as f32 / sample_rate as f32;
+        Some(self.cycles_per_block() as f32 * 100.0 / available)
+    }
+
+    /// Record the latency the patch introduces, in samples, e.g. the window size of an FFT
+    ///
+    /// This is local metadata only, nothing is sent to the OS: the OpenWare program vector has no field for
+    /// latency. Other parts of the patch can read it back with [Meta::latency_samples], to display or to
+    /// compensate for.
+    ///
+    /// ```
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// assert_eq!(0, pv.meta().latency_samples());
+    /// pv.meta().set_latency_samples(1024);
+    /// assert_eq!(1024, pv.meta().latency_samples());
+    /// ```
+    pub fn set_latency_samples(&mut self, samples: usize) {
+        self.latency_samples = samples;
+    }
+
+    /// The latency set with [Meta::set_latency_samples], in samples (default 0)
+    pub fn latency_samples(&self) -> usize {
+        self.latency_samples
+    }
+
     /// The checksum set by the OS before program start
     pub fn checksum(&self) -> ProgramVectorChecksum {
         self.checksum
@@ -77,6 +228,11 @@ impl Meta {
         self.hardware_version
     }
 
+    /// Get the capabilities of the hardware, see [Capabilities::from_hardware_version]
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::from_hardware_version(self.hardware_version)
+    }
+
     /// Get a slice of memory segments available for use in heap allocations
     pub fn memory_segments(&self) -> &[MemorySegment] {
         const MAX: usize = 5;
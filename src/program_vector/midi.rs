@@ -2,12 +2,12 @@ extern crate alloc;
 
 use core::{cell::RefCell, option::Option, ptr::NonNull};
 
-use alloc::boxed::Box;
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
 use spin::Mutex;
 
-use crate::midi_message::MidiMessage;
+use crate::midi_message::{MidiMessage, MidiStatus};
 
-use super::{ServiceCall, SystemFunction};
+use super::{ring::SpscRing, ServiceCall, SystemFunction};
 
 /// Send & receive midi messages
 ///
@@ -41,6 +41,25 @@ impl Midi {
     }
 
     /// Register a callback which is fired whenever a midi message is received
+    ///
+    /// ```
+    /// # use owl_patch::midi_message::MidiMessage;
+    /// # use owl_patch::test_harness;
+    /// # let mut pv = unsafe { test_harness::program_vector() };
+    /// let midi = pv.midi();
+    ///
+    /// // Echo notes an octave up
+    /// midi.on_receive(move |message| {
+    ///     if message.is_note_on() {
+    ///         midi.send(MidiMessage::note_on(message.channel(), message.note() + 12, message.velocity()));
+    ///     }
+    /// });
+    ///
+    /// test_harness::inject_midi(MidiMessage::note_on(0, 60, 100));
+    /// let sent = test_harness::sent_midi();
+    /// assert_eq!(1, sent.len());
+    /// assert_eq!((72, 100), (sent[0].note(), sent[0].velocity()));
+    /// ```
     pub fn on_receive(&self, callback: impl FnMut(MidiMessage) + Send + 'static) {
         RECEIVE_CALLBACK
             .lock()
@@ -48,6 +67,24 @@ impl Midi {
             .replace(Box::new(callback));
     }
 
+    /// Register a [MidiRouter] to handle received messages. This replaces any callback set with
+    /// [on_receive]
+    ///
+    /// ```
+    /// # use owl_patch::program_vector::{MidiFilter, MidiRouter};
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// let mut router = MidiRouter::new();
+    /// router.route(MidiFilter::Channel(0), |message| {
+    ///     // handle channel 1 messages
+    /// });
+    /// pv.midi().route(router);
+    /// ```
+    ///
+    /// [on_receive]: Midi::on_receive
+    pub fn route(&self, mut router: MidiRouter) {
+        self.on_receive(move |message| router.dispatch(&message));
+    }
+
     /// Send a midi message
     pub fn send(&self, message: MidiMessage) {
         if let Some(f) = self.send_callback {
@@ -57,6 +94,252 @@ impl Midi {
     }
 }
 
+/// Fixed size, lock-free queue of outgoing midi messages
+///
+/// Messages can be queued from anywhere (e.g. inside the audio closure) with [push], then sent together at a
+/// safe point with [flush]. It is single-producer / single-consumer: only one part of the patch should push,
+/// and only one should flush.
+///
+/// ```
+/// # use owl_patch::program_vector::MidiQueue;
+/// # use owl_patch::midi_message::MidiMessage;
+/// static QUEUE: MidiQueue<4> = MidiQueue::new();
+///
+/// assert!(QUEUE.push(MidiMessage::note_on(0, 60, 100)).is_ok());
+/// assert!(QUEUE.push(MidiMessage::note_on(0, 64, 100)).is_ok());
+/// assert!(QUEUE.push(MidiMessage::note_off(0, 60)).is_ok());
+///
+/// let notes: Vec<_> = core::iter::from_fn(|| QUEUE.pop()).map(|m| (m.note(), m.is_note_on())).collect();
+/// assert_eq!(vec![(60, true), (64, true), (60, false)], notes);
+/// ```
+///
+/// [push]: MidiQueue::push
+/// [flush]: MidiQueue::flush
+pub struct MidiQueue<const N: usize> {
+    ring: SpscRing<[u8; 4], N>,
+}
+
+impl<const N: usize> Default for MidiQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> MidiQueue<N> {
+    /// Create an empty queue, holding up to N messages. N must be at least 1
+    pub const fn new() -> Self {
+        Self {
+            ring: SpscRing::new([0; 4]),
+        }
+    }
+
+    /// Add a message to the back of the queue. If the queue is full, the message is given back.
+    ///
+    /// ```
+    /// # use owl_patch::program_vector::MidiQueue;
+    /// # use owl_patch::midi_message::MidiMessage;
+    /// let queue = MidiQueue::<1>::new();
+    ///
+    /// assert!(queue.push(MidiMessage::start()).is_ok());
+    /// assert!(queue.push(MidiMessage::stop()).is_err());
+    /// ```
+    pub fn push(&self, message: MidiMessage) -> Result<(), MidiMessage> {
+        let bytes @ [port, d0, d1, d2] = message.as_bytes();
+        if self.ring.push_with(|slot| *slot = bytes) {
+            Ok(())
+        } else {
+            Err(MidiMessage::new(port, d0, d1, d2))
+        }
+    }
+
+    /// Take the message from the front of the queue
+    pub fn pop(&self) -> Option<MidiMessage> {
+        self.ring
+            .pop_with(|&[port, d0, d1, d2]| MidiMessage::new(port, d0, d1, d2))
+    }
+
+    /// Number of messages waiting to be sent
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    /// True if no messages are waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Send all queued messages in order, returning how many were sent
+    pub fn flush(&self, midi: &Midi) -> usize {
+        core::iter::from_fn(|| self.pop())
+            .map(|message| midi.send(message))
+            .count()
+    }
+}
+
+/// Sends midi messages at sample-accurate times
+///
+/// Messages are scheduled with a delay in samples from the current position, then [tick] is called once for
+/// every sample inside the audio loop, sending each message as its sample is reached. Messages due on the same
+/// sample are sent in the order they were scheduled. The delay can be longer than a block.
+///
+/// ```
+/// # use owl_patch::program_vector::MidiScheduler;
+/// # use owl_patch::midi_message::MidiMessage;
+/// let mut scheduler = MidiScheduler::new();
+/// scheduler.schedule(3, MidiMessage::note_off(0, 60));
+/// scheduler.schedule(1, MidiMessage::note_on(0, 60, 100));
+/// scheduler.schedule(1, MidiMessage::note_on(0, 64, 100));
+///
+/// let mut sent = Vec::new();
+/// for sample in 0..4 {
+///     scheduler.tick_with(|message| sent.push((sample, message.note(), message.is_note_on())));
+/// }
+/// assert_eq!(vec![(1, 60, true), (1, 64, true), (3, 60, false)], sent);
+/// ```
+///
+/// Scheduling allocates if the scheduler is full, so create it [with_capacity] for use in the audio loop.
+///
+/// [tick]: MidiScheduler::tick
+/// [with_capacity]: MidiScheduler::with_capacity
+#[derive(Default)]
+pub struct MidiScheduler {
+    now: u64,
+    pending: VecDeque<(u64, MidiMessage)>,
+}
+
+impl MidiScheduler {
+    /// Create an empty scheduler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty scheduler, with space for `capacity` messages before allocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            now: 0,
+            pending: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Send `message` after `offset` samples. An offset of 0 sends it on the next tick
+    pub fn schedule(&mut self, offset: usize, message: MidiMessage) {
+        let due = self.now + offset as u64;
+        let index = self.pending.partition_point(|(time, _)| *time <= due);
+        self.pending.insert(index, (due, message));
+    }
+
+    /// Send any messages due on the current sample, then advance by one sample
+    pub fn tick(&mut self, midi: &Midi) {
+        self.tick_with(|message| midi.send(message));
+    }
+
+    /// As [tick](MidiScheduler::tick), but pass the due messages to `send`
+    pub fn tick_with(&mut self, mut send: impl FnMut(MidiMessage)) {
+        while let Some((_, message)) = self.pending.pop_front_if(|(time, _)| *time <= self.now) {
+            send(message);
+        }
+        self.now += 1;
+    }
+
+    /// Number of messages waiting to be sent
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// True if there are no messages waiting to be sent
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Drop all waiting messages
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// Which received messages are passed to a [MidiRouter] handler
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiFilter {
+    /// All messages
+    Any,
+    /// Channel messages on a channel (0-15)
+    Channel(u8),
+    /// Messages of a type, on any channel
+    Status(MidiStatus),
+    /// Messages of a type on a channel
+    ChannelStatus(u8, MidiStatus),
+}
+
+impl MidiFilter {
+    /// Does the filter let the message through?
+    pub fn matches(&self, message: &MidiMessage) -> bool {
+        let on_channel = |ch: u8| !message.is_system() && message.channel() == ch;
+        match *self {
+            Self::Any => true,
+            Self::Channel(ch) => on_channel(ch),
+            Self::Status(status) => message.status() == status,
+            Self::ChannelStatus(ch, status) => on_channel(ch) && message.status() == status,
+        }
+    }
+}
+
+/// Dispatches received midi messages to several handlers, by channel or message type
+///
+/// Every handler whose filter matches is called, in the order they were added. Register the router with
+/// [Midi::route].
+///
+/// ```
+/// # use owl_patch::program_vector::{MidiFilter, MidiRouter};
+/// # use owl_patch::midi_message::{MidiMessage, MidiStatus};
+/// use std::sync::{Arc, Mutex};
+///
+/// let received = Arc::new(Mutex::new(Vec::new()));
+/// let mut router = MidiRouter::new();
+///
+/// let log = received.clone();
+/// router.route(MidiFilter::Channel(0), move |m| log.lock().unwrap().push(("ch1", m.note())));
+/// let log = received.clone();
+/// router.route(MidiFilter::Status(MidiStatus::CONTROL_CHANGE), move |m| {
+///     log.lock().unwrap().push(("cc", m.controller_number()))
+/// });
+///
+/// router.dispatch(&MidiMessage::note_on(0, 60, 100));
+/// router.dispatch(&MidiMessage::note_on(1, 62, 100));
+/// router.dispatch(&MidiMessage::cc(1, 7, 100));
+///
+/// assert_eq!(vec![("ch1", 60), ("cc", 7)], *received.lock().unwrap());
+/// ```
+#[derive(Default)]
+pub struct MidiRouter {
+    #[allow(clippy::type_complexity)]
+    routes: Vec<(MidiFilter, Box<dyn FnMut(&MidiMessage) + Send>)>,
+}
+
+impl MidiRouter {
+    /// Create a router with no handlers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a handler for messages matching `filter`
+    pub fn route(
+        &mut self,
+        filter: MidiFilter,
+        handler: impl FnMut(&MidiMessage) + Send + 'static,
+    ) -> &mut Self {
+        self.routes.push((filter, Box::new(handler)));
+        self
+    }
+
+    /// Pass a message to every matching handler
+    pub fn dispatch(&mut self, message: &MidiMessage) {
+        self.routes
+            .iter_mut()
+            .filter(|(filter, _)| filter.matches(message))
+            .for_each(|(_, handler)| handler(message));
+    }
+}
+
 #[allow(clippy::type_complexity)]
 static RECEIVE_CALLBACK: Mutex<RefCell<Option<Box<dyn FnMut(MidiMessage) + Send>>>> =
     Mutex::new(RefCell::new(None));
@@ -2,29 +2,51 @@
 extern crate alloc;
 use num::FromPrimitive;
 
-use core::slice;
+use core::{
+    ffi::CStr,
+    slice,
+    sync::atomic::{AtomicI16, AtomicU16, AtomicU32},
+};
 
 use crate::{ffi::program_vector as ffi, volts_per_octave::VoltsPerSample};
 
 use ffi::ProgramVector as FfiProgramVector;
 
 mod audio;
-use audio::AudioFormat;
-pub use audio::{AudioBuffers, AudioSettings};
+#[cfg(not(target_os = "none"))]
+pub(crate) use audio::f32_adapter;
+pub(crate) use audio::AudioFormat;
+pub use audio::{AudioBuffers, AudioSettings, ChannelLayout};
 
 mod parameters;
-pub use parameters::Parameters;
+pub use parameters::{
+    ButtonEvent, ButtonEvents, Debouncer, GateToTrigger, OutputParameter, ParameterCurve,
+    Parameters, SmoothedParameter, TapTempo, TriggerToGate, BUTTON_EVENT_CAPACITY,
+};
 
 mod messages;
 use messages::Messages;
-pub use messages::{debug_message, error};
+pub use messages::{debug_message, debug_message_args, error, DebugLog};
+
+mod ring;
 
 mod midi;
-pub use midi::Midi;
+pub use midi::{Midi, MidiFilter, MidiQueue, MidiRouter, MidiScheduler};
 
 mod meta;
 pub use meta::*;
 
+mod leds;
+pub use leds::{LedColour, Leds};
+
+mod presets;
+pub use presets::PresetBank;
+
+#[cfg(feature = "talc")]
+mod heap;
+#[cfg(feature = "talc")]
+pub use heap::{heap_bytes_used, heap_free_bytes, heap_peak_bytes_used};
+
 mod service_call;
 use service_call::{ServiceCall, SystemFunction};
 
@@ -69,28 +91,27 @@ impl ProgramVector {
         let checksum = ProgramVectorChecksum::from_u8(pv.checksum)
             .expect("Program Vector checksum error - is your firmware up to date?");
 
-        let meta = Meta::new(
+        let mut meta = Meta::new(
             &pv.cycles_per_block,
             &mut pv.heap_bytes_used,
             checksum,
             pv.hardware_version,
             pv.heapLocations,
+            patch_name,
+            pv.registerPatch,
         );
 
         #[cfg(all(feature = "talc", target_os = "none"))]
         {
-            let mut talc = talc_heap::ALLOCATOR.lock();
             meta.memory_segments().iter().for_each(|seg| unsafe {
-                let _ = talc.claim(seg.into());
+                heap::ALLOCATOR.claim(seg.into());
             });
         }
 
-        // Register the patch by calling the provided function in the pv. It seems like the channel counts
-        // are ignored presently, the number of channels set in pv.audio_format is defined by the hardware
-        // The only thing it really does is display the patch name on devices with a screen
-        if let Some(register_patch) = pv.registerPatch {
-            unsafe { register_patch(patch_name, 2, 2) };
-        }
+        // Register the patch by calling the provided function in the pv. The channel counts only affect what
+        // the host displays, the number of channels set in pv.audio_format is defined by the hardware.
+        // Patches with different routing can register again with Meta::register_patch_io
+        meta.register_patch_io(2, 2);
 
         let (format, channels) = AudioFormat::parse(pv.audio_format);
         let audio_settings = AudioSettings {
@@ -100,18 +121,28 @@ impl ProgramVector {
             format,
         };
 
+        // The OS writes to the parameters and buttons from interrupts, so they are only ever accessed as atomics.
+        // Safety: the atomic types have the same size and alignment as the plain integers
         let parameters = Parameters::new(
-            unsafe { slice::from_raw_parts(pv.parameters, pv.parameters_size as usize) },
-            &pv.buttons,
+            unsafe {
+                slice::from_raw_parts(
+                    pv.parameters as *const AtomicI16,
+                    pv.parameters_size as usize,
+                )
+            },
+            unsafe { AtomicU16::from_ptr(&mut pv.buttons) },
             pv.registerPatchParameter,
             pv.setPatchParameter,
             pv.setButton,
             &mut pv.buttonChangedCallback,
         );
 
+        // The OS can change the sample rate and block size between blocks, so they are read as atomics too
         let audio = AudioBuffers::new(
             &pv.audio_input,
             &pv.audio_output,
+            unsafe { AtomicU32::from_ptr(&mut pv.audio_samplingrate) },
+            unsafe { AtomicU16::from_ptr(&mut pv.audio_blocksize) },
             audio_settings,
             pv.programReady,
         );
@@ -153,6 +184,11 @@ impl ProgramVector {
         self.parameters
     }
 
+    /// Get the status LED controller
+    pub fn leds(&mut self) -> Leds {
+        Leds::new(self.parameters)
+    }
+
     /// Get patch metadata
     pub fn meta(&mut self) -> &mut Meta {
         &mut self.meta
@@ -163,6 +199,57 @@ impl ProgramVector {
         &mut self.audio
     }
 
+    /// Read a resource stored on the device a chunk at a time, without loading all of it into memory
+    ///
+    /// Each chunk is read into `buffer` and passed to `f`. Every chunk is `buffer.len()` bytes long, apart from
+    /// the last which may be shorter. Returns the size of the resource.
+    ///
+    /// ```
+    /// # use owl_patch::test_harness;
+    /// let data: Vec<u8> = (0..10240).map(|n| n as u8).collect();
+    /// test_harness::add_resource("sample.raw", &data);
+    /// let mut pv = unsafe { test_harness::program_vector() };
+    ///
+    /// let mut buffer = [0; 4096];
+    /// let mut chunks = Vec::new();
+    /// let mut loaded = Vec::new();
+    /// let size = pv.load_resource_streamed(c"sample.raw", &mut buffer, |chunk| {
+    ///     chunks.push(chunk.len());
+    ///     loaded.extend_from_slice(chunk);
+    /// });
+    ///
+    /// assert_eq!(Ok(10240), size);
+    /// assert_eq!(vec![4096, 4096, 2048], chunks);
+    /// assert_eq!(data, loaded);
+    ///
+    /// assert!(pv.load_resource_streamed(c"missing.raw", &mut buffer, |_| {}).is_err());
+    /// ```
+    pub fn load_resource_streamed(
+        &mut self,
+        name: &CStr,
+        buffer: &mut [u8],
+        mut f: impl FnMut(&[u8]),
+    ) -> Result<usize, &str> {
+        if buffer.is_empty() {
+            return Err("buffer is empty");
+        }
+
+        let size = self.service_call.resource_size(name)?;
+        let mut offset = 0;
+        while offset < size {
+            let len = buffer.len().min(size - offset);
+            let read = self
+                .service_call
+                .read_resource(name, offset, &mut buffer[..len])?;
+            if read == 0 {
+                return Err("resource ended early");
+            }
+            f(&buffer[..read]);
+            offset += read;
+        }
+        Ok(size)
+    }
+
     /// Get calibrated volts per sample convertors as a pair (input, output)
     ///
     /// ```
@@ -179,33 +266,3 @@ impl ProgramVector {
         })
     }
 }
-
-#[cfg(all(feature = "talc", target_os = "none"))]
-mod talc_heap {
-    use talc::*;
-
-    use crate::ffi::program_vector::MemorySegment;
-
-    impl From<&MemorySegment> for talc::Span {
-        fn from(segment: &MemorySegment) -> talc::Span {
-            talc::Span::from_base_size(segment.location, segment.size as usize)
-        }
-    }
-
-    #[global_allocator]
-    pub static ALLOCATOR: Talck<spin::Mutex<()>, ErrOnOom> = Talc::new(ErrOnOom).lock();
-
-    /// get total bytes allocated at present
-    pub fn heap_bytes_used() -> usize {
-        ALLOCATOR.lock().get_counters().total_allocated_bytes as usize
-    }
-}
-
-#[cfg(all(feature = "talc", target_os = "none"))]
-pub use talc_heap::heap_bytes_used;
-
-#[cfg(all(feature = "talc", not(target_os = "none")))]
-#[doc(hidden)]
-pub fn heap_bytes_used() -> usize {
-    0
-}
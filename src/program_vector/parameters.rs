@@ -1,27 +1,81 @@
 extern crate alloc;
 
-use core::{cell::RefCell, ffi::c_char};
+use core::{
+    cell::RefCell,
+    ffi::c_char,
+    sync::atomic::{AtomicI16, AtomicU16, Ordering},
+};
 
-use alloc::{boxed::Box, ffi::CString};
+use alloc::{boxed::Box, ffi::CString, string::String};
 use num::FromPrimitive;
 use spin::Mutex;
 
+use super::ring::SpscRing;
+use crate::filter::{OnePole, OnePoleMode};
+
+#[cfg(target_os = "none")]
+use num_traits::Float as _;
+
 pub use crate::ffi::openware_midi_control::{PatchButtonId, PatchParameterId};
 
 /// Handles the Patch input and output parameters; knobs and buttons etc
+///
+/// # Sharing between callbacks
+///
+/// `Parameters` is `Copy`, `Send` and `Sync`, so a copy can be moved into each of the button and midi callbacks
+/// as well as used in the audio loop. It holds no state of its own, only function pointers for OS calls and
+/// views of the parameter and button values, which the OS updates from interrupts while the patch is running.
+///
+/// Those values are viewed as atomics, so every read is a fresh load from memory and the compiler can't cache
+/// or hoist it. Loads are relaxed: each value is read whole, but reading several gives no guarantee that they
+/// come from the same update, or that a value won't change between two reads. Use [Parameters::read_all] or
+/// [Parameters::snapshot] once per block if the patch needs values that stay fixed while it processes a block.
+///
+/// ```
+/// # use owl_patch::{midi_message::MidiMessage, PatchButtonId, PatchParameterId};
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// let parameters = pv.parameters();
+/// let midi = pv.midi();
+///
+/// parameters.on_button_changed(move |bid, state, _| {
+///     parameters.set_button(PatchButtonId::BUTTON_3, state != 0 && bid == PatchButtonId::BUTTON_1);
+/// });
+///
+/// midi.on_receive(move |message: MidiMessage| {
+///     if message.is_control_change() {
+///         parameters.set(PatchParameterId::PARAMETER_F, message.controller_value() as f32 / 127.0);
+///     }
+/// });
+///
+/// # owl_patch::test_harness::run_blocks(1);
+/// pv.audio().run_f32(|input, output| {
+///     let gain = parameters.get(PatchParameterId::PARAMETER_A);
+///     for (o, i) in output.samples_mut().iter_mut().zip(input.samples()) {
+///         *o = i * gain;
+///     }
+/// });
+/// ```
 #[derive(Clone, Copy)]
 pub struct Parameters {
-    parameters: &'static [i16],
-    buttons: &'static u16,
+    parameters: &'static [AtomicI16],
+    buttons: &'static AtomicU16,
     register_patch_parameter: Option<unsafe extern "C" fn(id: u8, name: *const c_char)>,
     set_patch_parameter: Option<unsafe extern "C" fn(id: u8, value: i16)>,
     set_button: Option<unsafe extern "C" fn(id: u8, state: u16, samples: u16)>,
 }
 
+// Parameters is moved into callbacks which must be Send, see above. Fail the build if a change to its fields
+// stops it being Send + Sync. This relies on the OS-owned values being atomics, plain references to memory
+// written by interrupts would not be sound to share
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Parameters>();
+};
+
 impl Parameters {
     pub(crate) fn new(
-        parameters: &'static [i16],
-        buttons: &'static u16,
+        parameters: &'static [AtomicI16],
+        buttons: &'static AtomicU16,
         register_patch_parameter: Option<unsafe extern "C" fn(id: u8, name: *const c_char)>,
         set_patch_parameter: Option<unsafe extern "C" fn(id: u8, value: i16)>,
         set_button: Option<unsafe extern "C" fn(id: u8, state: u16, samples: u16)>,
@@ -59,9 +113,44 @@ impl Parameters {
         }
     }
 
+    /// Register an input parameter
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
+    /// # use owl_patch::test_harness::parameter_name;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// parameters.register_input(PatchParameterId::PARAMETER_A, "volume");
+    /// assert_eq!(Some("volume".to_string()), parameter_name(PatchParameterId::PARAMETER_A));
+    /// ```
+    pub fn register_input(&self, pid: PatchParameterId, name: &str) {
+        self.register(pid, name.trim_end_matches('>'));
+    }
+
+    /// Register an output parameter. The name is postfixed with ">", which is how the OS tells them apart.
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
+    /// # use owl_patch::test_harness::parameter_name;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// parameters.register_output(PatchParameterId::PARAMETER_F, "envelope");
+    /// assert_eq!(Some("envelope>".to_string()), parameter_name(PatchParameterId::PARAMETER_F));
+    ///
+    /// // An existing postfix is not repeated
+    /// parameters.register_output(PatchParameterId::PARAMETER_G, "lfo>");
+    /// assert_eq!(Some("lfo>".to_string()), parameter_name(PatchParameterId::PARAMETER_G));
+    /// ```
+    pub fn register_output(&self, pid: PatchParameterId, name: &str) {
+        let mut name = String::from(name.trim_end_matches('>'));
+        name.push('>');
+        self.register(pid, &name);
+    }
+
     /// Get the value of an input parameter
     ///
-    /// return value will be in the range (-1.0..1.0)
+    /// The raw value is scaled so that 4096 => 1.0. Knobs are unipolar, so will generally be in the
+    /// range (0.0..1.0), but this is not guaranteed. Use [get_unipolar] or [get_bipolar] for a clamped range.
     ///
     /// ```
     /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
@@ -70,8 +159,132 @@ impl Parameters {
     /// parameters.register(PatchParameterId::PARAMETER_A, "volume");
     /// let value = parameters.get(PatchParameterId::PARAMETER_A);
     /// ```
+    ///
+    /// [get_unipolar]: Parameters::get_unipolar
+    /// [get_bipolar]: Parameters::get_bipolar
     pub fn get(&self, pid: PatchParameterId) -> f32 {
-        self.parameters[pid as usize] as f32 / 4096.0
+        self.parameters[pid as usize].load(Ordering::Relaxed) as f32 / 4096.0
+    }
+
+    /// Get the value of an input parameter, clamped to the range (0.0..1.0)
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
+    /// # use owl_patch::test_harness::set_parameter;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// set_parameter(PatchParameterId::PARAMETER_A, 0);
+    /// assert_eq!(0.0, parameters.get_unipolar(PatchParameterId::PARAMETER_A));
+    /// set_parameter(PatchParameterId::PARAMETER_A, 2048);
+    /// assert_eq!(0.5, parameters.get_unipolar(PatchParameterId::PARAMETER_A));
+    /// set_parameter(PatchParameterId::PARAMETER_A, i16::MAX);
+    /// assert_eq!(1.0, parameters.get_unipolar(PatchParameterId::PARAMETER_A));
+    /// set_parameter(PatchParameterId::PARAMETER_A, i16::MIN);
+    /// assert_eq!(0.0, parameters.get_unipolar(PatchParameterId::PARAMETER_A));
+    /// ```
+    pub fn get_unipolar(&self, pid: PatchParameterId) -> f32 {
+        self.get(pid).clamp(0.0, 1.0)
+    }
+
+    /// Get the value of an input parameter, mapped to the range (-1.0..1.0) so that the centre
+    /// position of a knob is 0.0
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
+    /// # use owl_patch::test_harness::set_parameter;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// set_parameter(PatchParameterId::PARAMETER_A, 0);
+    /// assert_eq!(-1.0, parameters.get_bipolar(PatchParameterId::PARAMETER_A));
+    /// set_parameter(PatchParameterId::PARAMETER_A, 2048);
+    /// assert_eq!(0.0, parameters.get_bipolar(PatchParameterId::PARAMETER_A));
+    /// set_parameter(PatchParameterId::PARAMETER_A, 4096);
+    /// assert_eq!(1.0, parameters.get_bipolar(PatchParameterId::PARAMETER_A));
+    /// set_parameter(PatchParameterId::PARAMETER_A, i16::MAX);
+    /// assert_eq!(1.0, parameters.get_bipolar(PatchParameterId::PARAMETER_A));
+    /// set_parameter(PatchParameterId::PARAMETER_A, i16::MIN);
+    /// assert_eq!(-1.0, parameters.get_bipolar(PatchParameterId::PARAMETER_A));
+    /// ```
+    pub fn get_bipolar(&self, pid: PatchParameterId) -> f32 {
+        self.get_unipolar(pid) * 2.0 - 1.0
+    }
+
+    /// Number of input parameters provided by the OS
+    pub fn count(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// Read the values of all input parameters into a slice, in [PatchParameterId] order, returning
+    /// the number of values written.
+    ///
+    /// If the slice is longer than [count], the extra values are left untouched. If it is shorter, only the
+    /// first `values.len()` parameters are read.
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
+    /// # use owl_patch::test_harness::set_parameter;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// parameters.register(PatchParameterId::PARAMETER_A, "A");
+    /// parameters.register(PatchParameterId::PARAMETER_C, "C");
+    /// set_parameter(PatchParameterId::PARAMETER_A, 1024);
+    /// set_parameter(PatchParameterId::PARAMETER_C, 2048);
+    ///
+    /// let mut values = [0.0; 16];
+    /// let count = parameters.read_all(&mut values);
+    ///
+    /// assert_eq!(parameters.count(), count);
+    /// assert_eq!(&[0.25, 0.0, 0.5], &values[..3]);
+    /// assert_eq!(parameters.get(PatchParameterId::PARAMETER_C), values[PatchParameterId::PARAMETER_C as usize]);
+    /// ```
+    ///
+    /// [count]: Parameters::count
+    pub fn read_all(&self, values: &mut [f32]) -> usize {
+        let count = values.len().min(self.parameters.len());
+        for (v, p) in values.iter_mut().zip(self.parameters) {
+            *v = p.load(Ordering::Relaxed) as f32 / 4096.0;
+        }
+        count
+    }
+
+    /// Read the values of the first `N` input parameters into an array. Any beyond [count] will be 0.0
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
+    /// # use owl_patch::test_harness::set_parameter;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// set_parameter(PatchParameterId::PARAMETER_B, 4096);
+    ///
+    /// let snapshot: [f32; 4] = parameters.snapshot();
+    /// assert_eq!([0.0, 1.0, 0.0, 0.0], snapshot);
+    /// ```
+    ///
+    /// [count]: Parameters::count
+    pub fn snapshot<const N: usize>(&self) -> [f32; N] {
+        let mut values = [0.0; N];
+        self.read_all(&mut values);
+        values
+    }
+
+    /// Get the value of an input parameter, mapped through a response curve
+    ///
+    /// The input is clamped to (0.0..1.0) before the curve is applied, see [ParameterCurve]
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::{Parameters, ParameterCurve}, PatchParameterId};
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// parameters.register(PatchParameterId::PARAMETER_A, "cutoff");
+    /// # owl_patch::test_harness::set_parameter(PatchParameterId::PARAMETER_A, 2048);
+    /// let cutoff = parameters.get_mapped(
+    ///     PatchParameterId::PARAMETER_A,
+    ///     ParameterCurve::Exponential { min: 20.0, max: 20000.0 },
+    /// );
+    /// # assert!((cutoff - 632.4555).abs() < 0.01);
+    /// ```
+    pub fn get_mapped(&self, pid: PatchParameterId, curve: ParameterCurve) -> f32 {
+        curve.apply(self.get(pid))
     }
 
     /// Set the value of an output parameter
@@ -85,12 +298,31 @@ impl Parameters {
     /// parameters.register(PatchParameterId::PARAMETER_F, "MyOutput>");
     /// parameters.set(PatchParameterId::PARAMETER_F, 0.5);
     /// ```
+    ///
+    /// Values outside the range are not clamped, see [Parameters::output] for a range-checked alternative
     pub fn set(&self, pid: PatchParameterId, value: f32) {
         if let Some(set_patch_parameter) = self.set_patch_parameter {
             unsafe { set_patch_parameter(pid as u8, (value * 4096.0) as i16) }
         }
     }
 
+    /// Get a handle for an output parameter, which can only be set
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// parameters.register(PatchParameterId::PARAMETER_F, "MyOutput>");
+    /// let output = parameters.output(PatchParameterId::PARAMETER_F);
+    /// output.set(0.5);
+    /// ```
+    pub fn output(&self, pid: PatchParameterId) -> OutputParameter {
+        OutputParameter {
+            parameters: *self,
+            pid,
+        }
+    }
+
     /// Set a callback for button changed events
     ///
     /// The 'value' parameter will generally be either 0 or 0xfff, 'samples' is the number
@@ -111,6 +343,39 @@ impl Parameters {
         BUTTON_CALLBACK.lock().replace(Some(Box::new(callback)));
     }
 
+    /// Take all button change events received since the last call, oldest first
+    ///
+    /// Calling this at the start of the audio closure gives the events which happened during the previous
+    /// block. Each event's `samples` value is the offset into that block at which the change happened, so
+    /// gates and triggers can be applied at the exact sample (delayed by one block).
+    ///
+    /// The events are queued without locking, so the OS can deliver them from any context, even an interrupt
+    /// taken while this is running. Up to [BUTTON_EVENT_CAPACITY] events are kept; if more arrive before they
+    /// are taken, the newest are dropped. Events for unknown button ids are ignored.
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchButtonId};
+    /// # use owl_patch::test_harness::change_button;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// change_button(PatchButtonId::BUTTON_1, 0xfff, 3);
+    /// change_button(PatchButtonId::BUTTON_2, 0xfff, 10);
+    /// change_button(PatchButtonId::BUTTON_1, 0, 20);
+    ///
+    /// let events: Vec<_> = parameters.button_events().map(|e| (e.id, e.pressed, e.samples)).collect();
+    /// assert_eq!(
+    ///     vec![
+    ///         (PatchButtonId::BUTTON_1, true, 3),
+    ///         (PatchButtonId::BUTTON_2, true, 10),
+    ///         (PatchButtonId::BUTTON_1, false, 20),
+    ///     ],
+    ///     events
+    /// );
+    /// assert_eq!(0, parameters.button_events().count());
+    /// ```
+    pub fn button_events(&self) -> ButtonEvents {
+        take_button_events()
+    }
+
     /// Get an input button value
     /// ```
     /// # use owl_patch::{program_vector::Parameters, PatchButtonId};
@@ -121,7 +386,7 @@ impl Parameters {
     /// }
     /// ```
     pub fn get_button(&self, bid: PatchButtonId) -> bool {
-        (*self.buttons) & (1 << bid as u8) != 0
+        self.buttons.load(Ordering::Relaxed) & (1 << bid as u8) != 0
     }
 
     /// Set an output button value
@@ -138,16 +403,639 @@ impl Parameters {
     }
 }
 
+/// Response curves for mapping input parameters, see [Parameters::get_mapped]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterCurve {
+    /// No change, 0.0..1.0
+    Linear,
+    /// Interpolates between `min` and `max` in log space, so the midpoint maps to the geometric mean.
+    /// Both `min` and `max` should be greater than zero. Useful for frequencies.
+    Exponential {
+        /// Output value at 0.0
+        min: f32,
+        /// Output value at 1.0
+        max: f32,
+    },
+    /// `log10(1 + 9x)`, 0.0..1.0, rising quickly then flattening out
+    Logarithmic,
+    /// `x * x`, 0.0..1.0
+    Quadratic,
+}
+
+impl ParameterCurve {
+    /// Map a value through the curve. The input is clamped to (0.0..1.0)
+    ///
+    /// ```
+    /// # use owl_patch::program_vector::ParameterCurve;
+    /// let curve = ParameterCurve::Exponential { min: 20.0, max: 20000.0 };
+    /// assert_eq!(20.0, curve.apply(0.0));
+    /// assert!((curve.apply(0.5) - 632.4555).abs() < 0.01);
+    /// assert!((curve.apply(1.0) - 20000.0).abs() < 0.01);
+    ///
+    /// let curve = ParameterCurve::Linear;
+    /// assert_eq!((0.0, 0.5, 1.0), (curve.apply(0.0), curve.apply(0.5), curve.apply(1.0)));
+    ///
+    /// let curve = ParameterCurve::Quadratic;
+    /// assert_eq!((0.0, 0.25, 1.0), (curve.apply(0.0), curve.apply(0.5), curve.apply(1.0)));
+    ///
+    /// let curve = ParameterCurve::Logarithmic;
+    /// assert_eq!((0.0, 1.0), (curve.apply(0.0), curve.apply(1.0)));
+    /// assert!((curve.apply(0.5) - 0.7404).abs() < 0.0001);
+    ///
+    /// // out of range inputs are clamped
+    /// assert_eq!(1.0, ParameterCurve::Linear.apply(1.5));
+    /// assert_eq!(0.0, ParameterCurve::Quadratic.apply(-0.5));
+    /// ```
+    pub fn apply(self, value: f32) -> f32 {
+        let x = value.clamp(0.0, 1.0);
+        match self {
+            ParameterCurve::Linear => x,
+            ParameterCurve::Exponential { min, max } => min * (max / min).powf(x),
+            ParameterCurve::Logarithmic => (1.0 + 9.0 * x).log10(),
+            ParameterCurve::Quadratic => x * x,
+        }
+    }
+}
+
+/// Largest raw value of a parameter. Parameters are read from and written to the hardware's 12 bit ADCs and
+/// DACs, so anything beyond this can't be represented on the output
+const PARAMETER_MAX: i16 = 4095;
+
+/// An output parameter, obtained from [Parameters::output]
+///
+/// Unlike [Parameters::set], values are clamped to the 12 bit range of the hardware DAC (±4095) rather than
+/// overflowing, or being truncated by the device
+#[derive(Clone, Copy)]
+pub struct OutputParameter {
+    parameters: Parameters,
+    pid: PatchParameterId,
+}
+
+impl OutputParameter {
+    /// Set the value of the output, clamped to (-1.0..1.0)
+    ///
+    /// The raw value sent to the device is `value * 4096`, limited to ±4095, so 1.0 gives the DAC's full scale
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Parameters, PatchParameterId};
+    /// # use owl_patch::test_harness::output_parameter;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// let output = parameters.output(PatchParameterId::PARAMETER_F);
+    ///
+    /// output.set(0.5);
+    /// assert_eq!(2048, output_parameter(PatchParameterId::PARAMETER_F));
+    ///
+    /// // Full scale is the 12 bit DAC maximum, not 4096
+    /// output.set(1.0);
+    /// assert_eq!(4095, output_parameter(PatchParameterId::PARAMETER_F));
+    /// output.set(-1.0);
+    /// assert_eq!(-4095, output_parameter(PatchParameterId::PARAMETER_F));
+    ///
+    /// output.set(2.0);
+    /// assert_eq!(4095, output_parameter(PatchParameterId::PARAMETER_F));
+    ///
+    /// output.set(-2.0);
+    /// assert_eq!(-4095, output_parameter(PatchParameterId::PARAMETER_F));
+    ///
+    /// // Large values don't wrap around
+    /// output.set(10.0);
+    /// assert_eq!(4095, output_parameter(PatchParameterId::PARAMETER_F));
+    /// ```
+    pub fn set(&self, value: f32) {
+        if let Some(set_patch_parameter) = self.parameters.set_patch_parameter {
+            let value = (value * 4096.0).clamp(-PARAMETER_MAX as f32, PARAMETER_MAX as f32);
+            unsafe { set_patch_parameter(self.pid as u8, value as i16) }
+        }
+    }
+
+    /// The parameter id of the output
+    pub fn id(&self) -> PatchParameterId {
+        self.pid
+    }
+}
+
+/// An input parameter with a one-pole smoothing filter applied, to avoid zipper noise when the value is
+/// used directly for gain or filter cutoff etc.
+///
+/// Call [update] once per block, then read the smoothed value with [value]. The time constant is the time
+/// taken to move ~63% of the way towards a new value.
+///
+/// ```
+/// # use owl_patch::{program_vector::SmoothedParameter, PatchParameterId};
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// let settings = pv.audio().settings;
+/// let block_rate = settings.sample_rate as f32 / settings.blocksize as f32;
+/// let mut volume = SmoothedParameter::new(pv.parameters(), PatchParameterId::PARAMETER_A, 10.0, block_rate);
+///
+/// # owl_patch::test_harness::set_parameter(PatchParameterId::PARAMETER_A, 2048);
+/// // in the audio loop
+/// volume.update();
+/// let gain = volume.value();
+/// # assert!(gain > 0.0 && gain < 0.5);
+/// ```
+///
+/// [update]: SmoothedParameter::update
+/// [value]: SmoothedParameter::value
+#[derive(Clone, Copy)]
+pub struct SmoothedParameter {
+    parameters: Parameters,
+    pid: PatchParameterId,
+    lowpass: OnePole,
+}
+
+impl SmoothedParameter {
+    /// Create a new smoothed parameter, starting at the current value of the input
+    ///
+    /// `block_rate` is the number of times per second [update] will be called, usually
+    /// `sample_rate / blocksize`
+    ///
+    /// [update]: SmoothedParameter::update
+    pub fn new(
+        parameters: Parameters,
+        pid: PatchParameterId,
+        time_constant_ms: f32,
+        block_rate: f32,
+    ) -> Self {
+        let mut lowpass = OnePole::with_coefficient(OnePoleMode::Lowpass, 0.0);
+        lowpass.set_state(parameters.get(pid));
+        let mut smoothed = Self {
+            parameters,
+            pid,
+            lowpass,
+        };
+        smoothed.set_time_constant(time_constant_ms, block_rate);
+        smoothed
+    }
+
+    /// Change the time constant
+    pub fn set_time_constant(&mut self, time_constant_ms: f32, block_rate: f32) {
+        let blocks = time_constant_ms * 0.001 * block_rate;
+        self.lowpass.set_coefficient(if blocks > 0.0 {
+            (-1.0 / blocks).exp()
+        } else {
+            0.0
+        });
+    }
+
+    /// Read the input parameter and move the smoothed value towards it. Call once per block.
+    ///
+    /// After the number of blocks matching the time constant, the value will be ~63% of the way
+    /// towards the target
+    /// ```
+    /// # use owl_patch::{program_vector::SmoothedParameter, PatchParameterId};
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// // 10 blocks per second, so a 1 second time constant is 10 blocks
+    /// let mut param = SmoothedParameter::new(pv.parameters(), PatchParameterId::PARAMETER_B, 1000.0, 10.0);
+    /// owl_patch::test_harness::set_parameter(PatchParameterId::PARAMETER_B, 4096);
+    ///
+    /// (0..10).for_each(|_| { param.update(); });
+    /// assert!((param.value() - 0.632).abs() < 0.001);
+    ///
+    /// (0..200).for_each(|_| { param.update(); });
+    /// assert!((param.value() - 1.0).abs() < 0.0001);
+    /// ```
+    pub fn update(&mut self) -> f32 {
+        self.lowpass.process(self.parameters.get(self.pid))
+    }
+
+    /// Get the current smoothed value
+    pub fn value(&self) -> f32 {
+        self.lowpass.state()
+    }
+}
+
+/// Filters a noisy button input, only reporting a change once the new state has been held for a number of
+/// consecutive updates
+///
+/// Call [update] once per block (or per sample) with the raw button state, e.g. from [Parameters::get_button].
+/// With a hold time of `n` updates, a change is reported on the `n`th consecutive update in the new state.
+///
+/// ```
+/// # use owl_patch::program_vector::Debouncer;
+/// let mut debouncer = Debouncer::new(3);
+/// let bouncing = [true, false, true, true, false, true, true, true, true, false, true];
+///
+/// let changes: Vec<_> = bouncing.iter().filter_map(|raw| debouncer.update(*raw)).collect();
+///
+/// assert_eq!(vec![true], changes);
+/// assert!(debouncer.state());
+/// ```
+///
+/// [update]: Debouncer::update
+#[derive(Clone, Copy, Debug)]
+pub struct Debouncer {
+    hold: u32,
+    count: u32,
+    state: bool,
+}
+
+impl Debouncer {
+    /// Create a new debouncer, initially released, which needs `hold` consecutive updates in a new state
+    /// before the change is reported. A hold of 0 or 1 reports every change immediately
+    pub fn new(hold: u32) -> Self {
+        Self {
+            hold: hold.max(1),
+            count: 0,
+            state: false,
+        }
+    }
+
+    /// Feed in the raw button state, returning the new state if it has just changed
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::Debouncer, PatchButtonId};
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// let mut gate = Debouncer::new(2);
+    /// // in the audio loop
+    /// if let Some(pressed) = gate.update(parameters.get_button(PatchButtonId::BUTTON_1)) {
+    ///     // trigger
+    /// }
+    /// ```
+    pub fn update(&mut self, raw: bool) -> Option<bool> {
+        if raw == self.state {
+            self.count = 0;
+            return None;
+        }
+
+        self.count += 1;
+        if self.count < self.hold {
+            return None;
+        }
+
+        self.count = 0;
+        self.state = raw;
+        Some(raw)
+    }
+
+    /// The current debounced state
+    pub fn state(&self) -> bool {
+        self.state
+    }
+}
+
+/// Converts a sustained gate into a trigger, which is only high for the first update after the gate rises
+///
+/// ```
+/// # use owl_patch::program_vector::GateToTrigger;
+/// let mut trigger = GateToTrigger::new();
+/// let gate = [false, true, true, true, true, false, false];
+///
+/// let triggers: Vec<_> = gate.iter().map(|g| trigger.process(*g)).collect();
+///
+/// assert_eq!(vec![false, true, false, false, false, false, false], triggers);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GateToTrigger {
+    last: bool,
+}
+
+impl GateToTrigger {
+    /// Create a new converter, with the gate initially low
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed in the gate state, once per block (or per sample). Returns true on a rising edge
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::GateToTrigger, PatchButtonId};
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// # let parameters = pv.parameters();
+    /// let mut trigger = GateToTrigger::new();
+    /// // in the audio loop
+    /// parameters.set_button(
+    ///     PatchButtonId::PUSHBUTTON,
+    ///     trigger.process(parameters.get_button(PatchButtonId::BUTTON_1)),
+    /// );
+    /// ```
+    pub fn process(&mut self, gate: bool) -> bool {
+        let trigger = gate && !self.last;
+        self.last = gate;
+        trigger
+    }
+}
+
+/// Converts a trigger into a gate, held high for a fixed number of updates
+///
+/// A trigger received while the gate is already high restarts it.
+///
+/// ```
+/// # use owl_patch::program_vector::TriggerToGate;
+/// let mut gate = TriggerToGate::new(3);
+/// let triggers = [true, false, false, false, false, true, false, true, false, false, false];
+///
+/// let gates: Vec<_> = triggers.iter().map(|t| gate.process(*t)).collect();
+///
+/// assert_eq!(
+///     vec![true, true, true, false, false, true, true, true, true, true, false],
+///     gates
+/// );
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct TriggerToGate {
+    length: u32,
+    remaining: u32,
+}
+
+impl TriggerToGate {
+    /// Create a new converter, with the gate held high for `length` updates after each trigger
+    pub fn new(length: u32) -> Self {
+        Self {
+            length,
+            remaining: 0,
+        }
+    }
+
+    /// Change the gate length
+    pub fn set_length(&mut self, length: u32) {
+        self.length = length;
+    }
+
+    /// Feed in the trigger state, once per block (or per sample). Returns the gate state
+    pub fn process(&mut self, trigger: bool) -> bool {
+        if trigger {
+            self.remaining = self.length;
+        }
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Detects tempo from taps on a button
+///
+/// Pass button events to [push] (see [Parameters::button_events]) and then call [advance] with the number of
+/// samples in the block, once per block. The tempo is the average of the intervals between the most recent taps
+/// (up to [TapTempo::MAX_TAPS]), available after the second tap. A gap longer than the timeout (default 2
+/// seconds) starts a new sequence of taps, but the previous tempo is kept until the next interval is measured.
+///
+/// ```
+/// # use owl_patch::{program_vector::{ButtonEvent, TapTempo}, PatchButtonId};
+/// // 120 bpm at 48kHz is 24000 samples per beat
+/// let mut tempo = TapTempo::new(48000.0, PatchButtonId::BUTTON_1);
+/// let tap = |samples| ButtonEvent { id: PatchButtonId::BUTTON_1, pressed: true, samples };
+///
+/// for block in 0..3000 {
+///     // a tap every 750 blocks of 32, at a different offset each time
+///     if block % 750 == 0 {
+///         tempo.push(&tap((block / 750) as u16 * 5));
+///     }
+///     tempo.advance(32);
+/// }
+///
+/// assert!((tempo.bpm().unwrap() - 120.0).abs() < 0.1);
+/// assert!((tempo.interval_seconds().unwrap() - 0.5).abs() < 0.001);
+/// ```
+///
+/// [push]: TapTempo::push
+/// [advance]: TapTempo::advance
+#[derive(Clone, Copy, Debug)]
+pub struct TapTempo {
+    sample_rate: f32,
+    bid: PatchButtonId,
+    timeout: usize,
+    // u64, so it won't wrap even at high sample rates. A 32-bit count would wrap after ~25 hours at 48kHz
+    now: u64,
+    last_tap: Option<u64>,
+    intervals: [usize; TapTempo::MAX_TAPS],
+    count: usize,
+    interval: Option<f32>,
+}
+
+impl TapTempo {
+    /// Maximum number of recent intervals averaged
+    pub const MAX_TAPS: usize = 4;
+
+    /// Create a new detector, listening to button `bid`, for audio running at `sample_rate`
+    pub fn new(sample_rate: f32, bid: PatchButtonId) -> Self {
+        Self {
+            sample_rate,
+            bid,
+            timeout: (sample_rate * 2.0) as usize,
+            now: 0,
+            last_tap: None,
+            intervals: [0; Self::MAX_TAPS],
+            count: 0,
+            interval: None,
+        }
+    }
+
+    /// Set the longest gap between taps, in seconds, before a new sequence of taps starts
+    pub fn set_timeout(&mut self, seconds: f32) {
+        self.timeout = (seconds * self.sample_rate) as usize;
+    }
+
+    /// Record that `samples` samples have passed
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::TapTempo, PatchButtonId};
+    /// let mut tempo = TapTempo::new(48000.0, PatchButtonId::BUTTON_1);
+    ///
+    /// // Keeps working after more than 2^32 samples, just over a day at 48kHz
+    /// tempo.advance(u32::MAX as usize);
+    /// tempo.advance(1000);
+    /// tempo.tap(0);
+    /// tempo.advance(24000);
+    /// tempo.tap(0);
+    /// assert_eq!(Some(120.0), tempo.bpm());
+    /// ```
+    pub fn advance(&mut self, samples: usize) {
+        self.now += samples as u64;
+    }
+
+    /// Handle a button event. Presses of the button are taps, anything else is ignored
+    pub fn push(&mut self, event: &ButtonEvent) {
+        if event.id == self.bid && event.pressed {
+            self.tap(event.samples);
+        }
+    }
+
+    /// Record a tap, `samples` into the current block
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::TapTempo, PatchButtonId};
+    /// let mut tempo = TapTempo::new(1000.0, PatchButtonId::BUTTON_1);
+    /// tempo.tap(0);
+    /// assert_eq!(None, tempo.bpm());
+    ///
+    /// tempo.advance(500);
+    /// tempo.tap(0);
+    /// assert_eq!(Some(120.0), tempo.bpm());
+    ///
+    /// // After the timeout, the old tempo is kept until two more taps
+    /// tempo.advance(3000);
+    /// tempo.tap(0);
+    /// assert_eq!(Some(120.0), tempo.bpm());
+    /// tempo.advance(250);
+    /// tempo.tap(0);
+    /// assert_eq!(Some(240.0), tempo.bpm());
+    /// ```
+    pub fn tap(&mut self, samples: u16) {
+        let time = self.now + samples as u64;
+
+        match self.last_tap {
+            Some(last) if time > last && time - last <= self.timeout as u64 => {
+                self.intervals[self.count % Self::MAX_TAPS] = (time - last) as usize;
+                self.count += 1;
+
+                let taps = self.count.min(Self::MAX_TAPS);
+                let total: usize = self.intervals[..taps].iter().sum();
+                self.interval = Some(total as f32 / taps as f32);
+            }
+            _ => self.count = 0,
+        }
+        self.last_tap = Some(time);
+    }
+
+    /// The average interval between taps in samples, None until two taps have been received
+    pub fn interval_samples(&self) -> Option<f32> {
+        self.interval
+    }
+
+    /// The average interval between taps in seconds
+    pub fn interval_seconds(&self) -> Option<f32> {
+        Some(self.interval? / self.sample_rate)
+    }
+
+    /// The tempo in beats per minute, taking each tap as one beat
+    pub fn bpm(&self) -> Option<f32> {
+        Some(60.0 / self.interval_seconds()?)
+    }
+}
+
+/// Maximum number of button events held between calls to [Parameters::button_events]
+pub const BUTTON_EVENT_CAPACITY: usize = 16;
+
+/// A button state change, see [Parameters::button_events]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ButtonEvent {
+    /// Which button changed
+    pub id: PatchButtonId,
+    /// New button state
+    pub pressed: bool,
+    /// Offset into the audio block at which the change happened
+    pub samples: u16,
+}
+
+/// Queue of button events, iterates oldest first
+#[derive(Clone, Copy)]
+pub struct ButtonEvents {
+    events: [Option<ButtonEvent>; BUTTON_EVENT_CAPACITY],
+    head: usize,
+    len: usize,
+}
+
+impl ButtonEvents {
+    const fn new() -> Self {
+        Self {
+            events: [None; BUTTON_EVENT_CAPACITY],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, event: ButtonEvent) {
+        let tail = (self.head + self.len) % BUTTON_EVENT_CAPACITY;
+        self.events[tail] = Some(event);
+        if self.len == BUTTON_EVENT_CAPACITY {
+            self.head = (self.head + 1) % BUTTON_EVENT_CAPACITY;
+        } else {
+            self.len += 1;
+        }
+    }
+}
+
+impl Default for ButtonEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for ButtonEvents {
+    type Item = ButtonEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head].take();
+        self.head = (self.head + 1) % BUTTON_EVENT_CAPACITY;
+        self.len -= 1;
+        event
+    }
+}
+
+// Written by the OS callback and read by the patch, so a single producer and a single consumer
+static BUTTON_EVENTS: SpscRing<ButtonEvent, BUTTON_EVENT_CAPACITY> = SpscRing::new(ButtonEvent {
+    id: PatchButtonId::BUTTON_1,
+    pressed: false,
+    samples: 0,
+});
+
+fn take_button_events() -> ButtonEvents {
+    let mut events = ButtonEvents::new();
+    while let Some(event) = BUTTON_EVENTS.pop_with(|event| *event) {
+        events.push(event);
+    }
+    events
+}
+
 #[allow(clippy::type_complexity)]
 static BUTTON_CALLBACK: Mutex<RefCell<Option<Box<dyn FnMut(PatchButtonId, u16, u16) + Send>>>> =
     Mutex::new(RefCell::new(None));
 
 pub extern "C" fn button_changed(bid: u8, state: u16, samples: u16) {
-    if let Some(callback) = BUTTON_CALLBACK.lock().borrow_mut().as_mut() {
-        callback(
-            PatchButtonId::from_u8(bid).unwrap_or(PatchButtonId::BUTTON_1),
-            state,
+    let Some(bid) = PatchButtonId::from_u8(bid) else {
+        return;
+    };
+
+    BUTTON_EVENTS.push_with(|event| {
+        *event = ButtonEvent {
+            id: bid,
+            pressed: state != 0,
             samples,
+        }
+    });
+
+    if let Some(callback) = BUTTON_CALLBACK.lock().borrow_mut().as_mut() {
+        callback(bid, state, samples);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_button_ids_are_dropped() {
+        take_button_events();
+        button_changed(PatchButtonId::BUTTON_2 as u8, 0xfff, 4);
+        button_changed(0xee, 0xfff, 5);
+        button_changed(PatchButtonId::BUTTON_2 as u8, 0, 6);
+
+        let events: alloc::vec::Vec<_> = take_button_events().collect();
+        assert_eq!(2, events.len());
+        assert!(events.iter().all(|e| e.id == PatchButtonId::BUTTON_2));
+        assert_eq!([4, 6], [events[0].samples, events[1].samples]);
+    }
+
+    #[test]
+    fn test_button_events_keep_oldest_when_full() {
+        take_button_events();
+        for n in 0..BUTTON_EVENT_CAPACITY as u16 + 4 {
+            button_changed(PatchButtonId::BUTTON_3 as u8, 0xfff, n);
+        }
+
+        let samples: alloc::vec::Vec<_> = take_button_events().map(|e| e.samples).collect();
+        assert_eq!(
+            (0..BUTTON_EVENT_CAPACITY as u16).collect::<alloc::vec::Vec<_>>(),
+            samples
         );
+        assert_eq!(0, take_button_events().count());
     }
 }
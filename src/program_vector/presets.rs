@@ -0,0 +1,234 @@
+use num::FromPrimitive;
+
+use super::{ButtonEvent, Parameters};
+use crate::interpolation::Lerp;
+use crate::{PatchButtonId, PatchParameterId};
+
+/// A bank of `SLOTS` parameter snapshots, each holding the first `N` parameter values
+///
+/// Snapshots can be captured from the current input parameters and recalled later, either by writing them
+/// back to the parameters or just reading them for the patch's own use.
+///
+/// ```
+/// # use owl_patch::{program_vector::PresetBank, PatchParameterId};
+/// # use owl_patch::test_harness::{output_parameter, set_parameter};
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// let parameters = pv.parameters();
+/// let mut bank = PresetBank::<4, 2>::new();
+///
+/// set_parameter(PatchParameterId::PARAMETER_A, 1024);
+/// set_parameter(PatchParameterId::PARAMETER_B, 2048);
+/// bank.capture(0, &parameters);
+///
+/// set_parameter(PatchParameterId::PARAMETER_A, 4096);
+/// assert_eq!(Some(&[0.25, 0.5]), bank.get(0));
+/// assert_eq!(None, bank.get(1));
+///
+/// // Recalling writes the values back to the parameters
+/// assert_eq!(Some(&[0.25, 0.5]), bank.recall(0, &parameters));
+/// assert_eq!(1024, output_parameter(PatchParameterId::PARAMETER_A));
+/// assert_eq!(2048, output_parameter(PatchParameterId::PARAMETER_B));
+/// assert_eq!(Some(0), bank.current());
+/// ```
+#[derive(Clone, Copy)]
+pub struct PresetBank<const SLOTS: usize, const N: usize> {
+    slots: [Option<[f32; N]>; SLOTS],
+    buttons: [Option<PatchButtonId>; SLOTS],
+    current: Option<usize>,
+}
+
+impl<const SLOTS: usize, const N: usize> PresetBank<SLOTS, N> {
+    /// Create a new bank with all slots empty
+    pub const fn new() -> Self {
+        Self {
+            slots: [None; SLOTS],
+            buttons: [None; SLOTS],
+            current: None,
+        }
+    }
+
+    /// Capture the current input parameter values into a slot
+    ///
+    /// # Panics
+    /// If `slot` is out of range
+    pub fn capture(&mut self, slot: usize, parameters: &Parameters) {
+        self.store(slot, parameters.snapshot());
+    }
+
+    /// Store a set of values into a slot
+    ///
+    /// # Panics
+    /// If `slot` is out of range
+    /// ```should_panic
+    /// # use owl_patch::program_vector::PresetBank;
+    /// let mut bank = PresetBank::<2, 1>::new();
+    /// bank.store(2, [0.5]);
+    /// ```
+    pub fn store(&mut self, slot: usize, values: [f32; N]) {
+        self.slots[slot] = Some(values);
+        self.current = Some(slot);
+    }
+
+    /// Empty a slot
+    ///
+    /// # Panics
+    /// If `slot` is out of range
+    /// ```should_panic
+    /// # use owl_patch::program_vector::PresetBank;
+    /// let mut bank = PresetBank::<2, 1>::new();
+    /// bank.clear(2);
+    /// ```
+    pub fn clear(&mut self, slot: usize) {
+        self.slots[slot] = None;
+        if self.current == Some(slot) {
+            self.current = None;
+        }
+    }
+
+    /// Get the values stored in a slot, None if the slot is empty or out of range
+    pub fn get(&self, slot: usize) -> Option<&[f32; N]> {
+        self.slots.get(slot)?.as_ref()
+    }
+
+    /// Recall a slot, writing its values to the parameters in [PatchParameterId] order
+    ///
+    /// Returns the values, or None (and writes nothing) if the slot is empty
+    pub fn recall(&mut self, slot: usize, parameters: &Parameters) -> Option<&[f32; N]> {
+        let values = self.slots.get(slot)?.as_ref()?;
+        write_parameters(values, parameters);
+        self.current = Some(slot);
+        Some(values)
+    }
+
+    /// Morph between two slots, `amount` 0.0 giving slot `a` and 1.0 giving slot `b`
+    ///
+    /// The amount is clamped to (0.0..1.0). Returns None if either slot is empty. The result can be used
+    /// directly, or written back to the parameters with [PresetBank::morph_to].
+    ///
+    /// ```
+    /// # use owl_patch::program_vector::PresetBank;
+    /// let mut bank = PresetBank::<2, 2>::new();
+    /// bank.store(0, [0.0, 1.0]);
+    /// bank.store(1, [1.0, 0.5]);
+    ///
+    /// assert_eq!(Some([0.0, 1.0]), bank.morph(0, 1, 0.0));
+    /// assert_eq!(Some([0.5, 0.75]), bank.morph(0, 1, 0.5));
+    /// assert_eq!(Some([1.0, 0.5]), bank.morph(0, 1, 1.0));
+    /// assert_eq!(Some([1.0, 0.5]), bank.morph(0, 1, 2.0));
+    ///
+    /// bank.clear(1);
+    /// assert_eq!(None, bank.morph(0, 1, 0.5));
+    /// ```
+    pub fn morph(&self, a: usize, b: usize, amount: f32) -> Option<[f32; N]> {
+        Some(self.get(a)?.lerp(*self.get(b)?, amount.clamp(0.0, 1.0)))
+    }
+
+    /// Morph between two slots, as [PresetBank::morph], and write the result to the parameters
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::PresetBank, PatchParameterId};
+    /// # use owl_patch::test_harness::output_parameter;
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// let parameters = pv.parameters();
+    /// let mut bank = PresetBank::<2, 1>::new();
+    /// bank.store(0, [0.25]);
+    /// bank.store(1, [0.75]);
+    ///
+    /// assert_eq!(Some([0.5]), bank.morph_to(0, 1, 0.5, &parameters));
+    /// assert_eq!(2048, output_parameter(PatchParameterId::PARAMETER_A));
+    /// ```
+    pub fn morph_to(
+        &self,
+        a: usize,
+        b: usize,
+        amount: f32,
+        parameters: &Parameters,
+    ) -> Option<[f32; N]> {
+        let values = self.morph(a, b, amount)?;
+        write_parameters(&values, parameters);
+        Some(values)
+    }
+
+    /// The slot most recently captured, stored or recalled
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Assign a button to a slot, for use with [PresetBank::handle_button]
+    ///
+    /// # Panics
+    /// If `slot` is out of range
+    /// ```should_panic
+    /// # use owl_patch::{program_vector::PresetBank, PatchButtonId};
+    /// let mut bank = PresetBank::<2, 1>::new();
+    /// bank.assign_button(2, PatchButtonId::BUTTON_1);
+    /// ```
+    pub fn assign_button(&mut self, slot: usize, bid: PatchButtonId) {
+        self.buttons[slot] = Some(bid);
+    }
+
+    /// Handle a button event, see [Parameters::button_events]
+    ///
+    /// Pressing a button assigned to an empty slot captures the current parameters into it, pressing it again
+    /// recalls them. Returns the slot, if one was captured or recalled.
+    ///
+    /// ```
+    /// # use owl_patch::{program_vector::PresetBank, PatchButtonId, PatchParameterId};
+    /// # use owl_patch::test_harness::{change_button, output_parameter, set_parameter};
+    /// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+    /// let parameters = pv.parameters();
+    /// let mut bank = PresetBank::<2, 1>::new();
+    /// bank.assign_button(0, PatchButtonId::BUTTON_1);
+    /// bank.assign_button(1, PatchButtonId::BUTTON_2);
+    ///
+    /// let mut handle_events = |bank: &mut PresetBank<2, 1>| -> Vec<usize> {
+    ///     parameters
+    ///         .button_events()
+    ///         .filter_map(|event| bank.handle_button(event, &parameters))
+    ///         .collect()
+    /// };
+    ///
+    /// // First press captures
+    /// set_parameter(PatchParameterId::PARAMETER_A, 2048);
+    /// change_button(PatchButtonId::BUTTON_2, 0xfff, 0);
+    /// change_button(PatchButtonId::BUTTON_2, 0, 10);
+    /// assert_eq!(vec![1], handle_events(&mut bank));
+    /// assert_eq!(Some(&[0.5]), bank.get(1));
+    ///
+    /// // Second press recalls
+    /// set_parameter(PatchParameterId::PARAMETER_A, 0);
+    /// change_button(PatchButtonId::BUTTON_2, 0xfff, 0);
+    /// assert_eq!(vec![1], handle_events(&mut bank));
+    /// assert_eq!(Some(&[0.5]), bank.get(1));
+    /// assert_eq!(2048, output_parameter(PatchParameterId::PARAMETER_A));
+    /// ```
+    pub fn handle_button(&mut self, event: ButtonEvent, parameters: &Parameters) -> Option<usize> {
+        if !event.pressed {
+            return None;
+        }
+        // Buttons can only be assigned to slots in range, so this can't panic
+        let slot = self.buttons.iter().position(|b| *b == Some(event.id))?;
+
+        if self.slots[slot].is_some() {
+            self.recall(slot, parameters);
+        } else {
+            self.capture(slot, parameters);
+        }
+        Some(slot)
+    }
+}
+
+impl<const SLOTS: usize, const N: usize> Default for PresetBank<SLOTS, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Write values to the parameters in [PatchParameterId] order
+fn write_parameters(values: &[f32], parameters: &Parameters) {
+    for (n, value) in values.iter().enumerate() {
+        if let Some(pid) = PatchParameterId::from_usize(n) {
+            parameters.output(pid).set(*value);
+        }
+    }
+}
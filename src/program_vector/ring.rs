@@ -0,0 +1,135 @@
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Fixed size, lock-free, single-producer / single-consumer ring of `N` slots
+///
+/// The read and write indices count modulo `2 * N` rather than wrapping at `usize::MAX`, so any `N` works: a full
+/// ring (indices `N` apart) can be told apart from an empty one (indices equal), and the slot for an index is
+/// always `index % N`.
+pub(crate) struct SpscRing<T, const N: usize> {
+    slots: UnsafeCell<[T; N]>,
+    read: AtomicUsize,
+    write: AtomicUsize,
+}
+
+// Safety: each slot is only written by the producer before `write` is published, and only read by the consumer
+// before `read` is published
+unsafe impl<T: Send, const N: usize> Sync for SpscRing<T, N> {}
+
+impl<T: Copy, const N: usize> SpscRing<T, N> {
+    /// Create an empty ring, with every slot set to `init`
+    pub const fn new(init: T) -> Self {
+        const { assert!(N > 0 && N <= usize::MAX / 2, "ring size out of range") };
+        Self {
+            slots: UnsafeCell::new([init; N]),
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<T, const N: usize> SpscRing<T, N> {
+    const fn next(index: usize) -> usize {
+        if index == 2 * N - 1 {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    const fn distance(read: usize, write: usize) -> usize {
+        if write >= read {
+            write - read
+        } else {
+            write + 2 * N - read
+        }
+    }
+
+    fn slot(&self, index: usize) -> *mut T {
+        // Safety: index % N is in bounds
+        unsafe { (self.slots.get() as *mut T).add(index % N) }
+    }
+
+    /// Fill the next free slot with `f`. Returns false, without calling `f`, if the ring is full
+    pub fn push_with(&self, f: impl FnOnce(&mut T)) -> bool {
+        let write = self.write.load(Ordering::Relaxed);
+        if Self::distance(self.read.load(Ordering::Acquire), write) >= N {
+            return false;
+        }
+        // Safety: the slot is outside of the readable range, so the consumer won't touch it
+        f(unsafe { &mut *self.slot(write) });
+        self.write.store(Self::next(write), Ordering::Release);
+        true
+    }
+
+    /// Pass the oldest slot to `f` and remove it. Returns None, without calling `f`, if the ring is empty
+    pub fn pop_with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let read = self.read.load(Ordering::Relaxed);
+        if read == self.write.load(Ordering::Acquire) {
+            return None;
+        }
+        // Safety: the slot is inside the readable range, so the producer won't touch it
+        let result = f(unsafe { &*self.slot(read) });
+        self.read.store(Self::next(read), Ordering::Release);
+        Some(result)
+    }
+
+    /// Number of slots waiting to be read
+    pub fn len(&self) -> usize {
+        Self::distance(
+            self.read.load(Ordering::Acquire),
+            self.write.load(Ordering::Acquire),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpscRing;
+
+    #[test]
+    fn test_wraps_with_any_size() {
+        // 3 isn't a power of two, run through the indices' full range many times
+        let ring = SpscRing::<u32, 3>::new(0);
+        let mut next_in = 0;
+        let mut next_out = 0;
+
+        for round in 0..100 {
+            let pushes = round % 4;
+            for _ in 0..pushes {
+                if ring.push_with(|slot| *slot = next_in) {
+                    next_in += 1;
+                }
+            }
+            assert_eq!(next_in - next_out, ring.len() as u32);
+            assert!(ring.len() <= 3);
+
+            for _ in 0..round % 3 {
+                if let Some(value) = ring.pop_with(|slot| *slot) {
+                    assert_eq!(next_out, value);
+                    next_out += 1;
+                }
+            }
+        }
+        assert!(next_out > 50);
+    }
+
+    #[test]
+    fn test_full_and_empty() {
+        let ring = SpscRing::<u8, 3>::new(0);
+        for _ in 0..5 {
+            assert_eq!(None, ring.pop_with(|slot| *slot));
+            assert!(ring.push_with(|slot| *slot = 1));
+            assert!(ring.push_with(|slot| *slot = 2));
+            assert!(ring.push_with(|slot| *slot = 3));
+            assert!(!ring.push_with(|_| panic!("ring is full")));
+            assert_eq!(3, ring.len());
+            assert_eq!(Some(1), ring.pop_with(|slot| *slot));
+            assert_eq!(Some(2), ring.pop_with(|slot| *slot));
+            assert_eq!(Some(3), ring.pop_with(|slot| *slot));
+            assert_eq!(0, ring.len());
+        }
+    }
+}
@@ -133,6 +133,45 @@ impl ServiceCall {
             .map(|ptr| unsafe { slice::from_raw_parts(ptr.as_ptr(), size) })
     }
 
+    /// Size in bytes of the named resource
+    pub fn resource_size(&mut self, name: &CStr) -> Result<usize, &str> {
+        // With a null data pointer the OS only looks the resource up, and reports its size
+        let mut data: *mut u8 = core::ptr::null_mut();
+        let mut offset: usize = 0;
+        let mut size: usize = 0;
+        let mut args = [
+            name.as_ptr() as *mut _,
+            &mut data as *mut *mut u8 as *mut _,
+            &mut offset as *mut usize as *mut _,
+            &mut size as *mut usize as *mut _,
+        ];
+
+        self.service_call(ServiceCallType::OwlServiceLoadResource, &mut args)
+            .map(|_| size)
+    }
+
+    /// Copy part of the named resource, starting `offset` bytes in, into `buffer`. Returns the number of bytes
+    /// copied, which is less than the buffer's length if the end of the resource is reached
+    pub fn read_resource(
+        &mut self,
+        name: &CStr,
+        offset: usize,
+        buffer: &mut [u8],
+    ) -> Result<usize, &str> {
+        let mut data = buffer.as_mut_ptr();
+        let mut offset = offset;
+        let mut size = buffer.len();
+        let mut args = [
+            name.as_ptr() as *mut _,
+            &mut data as *mut *mut u8 as *mut _,
+            &mut offset as *mut usize as *mut _,
+            &mut size as *mut usize as *mut _,
+        ];
+
+        self.service_call(ServiceCallType::OwlServiceLoadResource, &mut args)
+            .map(|_| size.min(buffer.len()))
+    }
+
     pub fn device_parameters(&mut self) -> DeviceParameters {
         const IN_OFFSET: &[u8; 3usize] = b"IO\0";
         const IN_SCALAR: &[u8; 3usize] = b"IS\0";
@@ -0,0 +1,204 @@
+//! Decoding of resource data (samples, wavetables etc.)
+//!
+//! Resource data is a plain byte array, with no alignment guarantees, so samples are decoded from bytes
+//! rather than by casting the slice.
+use crate::sample_buffer::{Buffer, Mono, MutableContainer};
+
+/// Sample format of raw PCM data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PcmFormat {
+    /// 16 bit signed, little-endian
+    I16Le,
+    /// 32 bit signed, little-endian
+    I32Le,
+}
+
+impl PcmFormat {
+    /// Number of bytes per sample
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            Self::I16Le => 2,
+            Self::I32Le => 4,
+        }
+    }
+
+    /// Decode a single sample into the range (-1.0..1.0)
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::I16Le => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            Self::I32Le => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2147483648.0
+            }
+        }
+    }
+}
+
+/// Decode raw PCM data into a float buffer, returning the number of samples written
+///
+/// Decoding stops at whichever is shorter of the buffer and the data. Any trailing partial sample is
+/// ignored.
+/// ```
+/// # use owl_patch::resource::{load_samples_into, PcmFormat};
+/// # use owl_patch::sample_buffer::Buffer;
+/// let data = [0x00, 0x00, 0x00, 0x40, 0x00, 0xc0, 0xff, 0x7f, 0x00, 0x80];
+/// let mut buffer = Buffer::new_mono(8);
+///
+/// assert_eq!(5, load_samples_into(&data, &mut buffer, PcmFormat::I16Le));
+/// assert_eq!(&[0.0, 0.5, -0.5, 32767.0 / 32768.0, -1.0], &buffer[..5]);
+/// ```
+///
+/// ```
+/// # use owl_patch::resource::{load_samples_into, PcmFormat};
+/// # use owl_patch::sample_buffer::Buffer;
+/// let data = [0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x80, 0xff];
+/// let mut buffer = Buffer::new_mono(8);
+///
+/// assert_eq!(2, load_samples_into(&data, &mut buffer, PcmFormat::I32Le));
+/// assert_eq!(&[0.5, -1.0], &buffer[..2]);
+/// ```
+pub fn load_samples_into<C: MutableContainer<Item = f32>>(
+    data: &[u8],
+    buffer: &mut Buffer<Mono, C>,
+    format: PcmFormat,
+) -> usize {
+    buffer
+        .iter_mut()
+        .zip(data.chunks_exact(format.bytes_per_sample()))
+        .map(|(s, bytes)| *s = format.decode(bytes))
+        .count()
+}
+
+/// A parsed RIFF/WAVE file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Wav<'a> {
+    /// Number of interleaved channels
+    pub channels: u16,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Bits per sample
+    pub bits_per_sample: u16,
+    /// The raw PCM data
+    pub data: &'a [u8],
+}
+
+const WAVE_FORMAT_PCM: u16 = 1;
+
+impl<'a> Wav<'a> {
+    /// Parse a WAV file held in memory. Only uncompressed PCM data is supported.
+    ///
+    /// ```
+    /// # use owl_patch::resource::{Wav, PcmFormat};
+    /// let mut file = Vec::new();
+    /// file.extend_from_slice(b"RIFF");
+    /// file.extend_from_slice(&40u32.to_le_bytes());
+    /// file.extend_from_slice(b"WAVEfmt ");
+    /// file.extend_from_slice(&16u32.to_le_bytes());
+    /// file.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    /// file.extend_from_slice(&1u16.to_le_bytes()); // mono
+    /// file.extend_from_slice(&48000u32.to_le_bytes());
+    /// file.extend_from_slice(&96000u32.to_le_bytes()); // byte rate
+    /// file.extend_from_slice(&2u16.to_le_bytes()); // block align
+    /// file.extend_from_slice(&16u16.to_le_bytes());
+    /// file.extend_from_slice(b"data");
+    /// file.extend_from_slice(&4u32.to_le_bytes());
+    /// file.extend_from_slice(&[0x00, 0x40, 0x00, 0xc0]);
+    ///
+    /// let wav = Wav::parse(&file).unwrap();
+    /// assert_eq!(1, wav.channels);
+    /// assert_eq!(48000, wav.sample_rate);
+    /// assert_eq!(16, wav.bits_per_sample);
+    /// assert_eq!(&[0x00, 0x40, 0x00, 0xc0], wav.data);
+    /// assert_eq!(Some(PcmFormat::I16Le), wav.pcm_format());
+    ///
+    /// // Non-PCM formats are rejected
+    /// file[20] = 3;
+    /// assert!(Wav::parse(&file).is_err());
+    /// ```
+    ///
+    /// Chunks which claim to be bigger than the rest of the file are rejected, rather than read past the end
+    /// ```
+    /// # use owl_patch::resource::Wav;
+    /// let mut file = Vec::new();
+    /// file.extend_from_slice(b"RIFF");
+    /// file.extend_from_slice(&4u32.to_le_bytes());
+    /// file.extend_from_slice(b"WAVELIST");
+    /// file.extend_from_slice(&u32::MAX.to_le_bytes());
+    /// file.extend_from_slice(&[0; 8]);
+    ///
+    /// assert_eq!(Err("chunk extends past the end of the file"), Wav::parse(&file));
+    ///
+    /// // A data chunk cut short
+    /// let mut file = Vec::new();
+    /// file.extend_from_slice(b"RIFF");
+    /// file.extend_from_slice(&40u32.to_le_bytes());
+    /// file.extend_from_slice(b"WAVEfmt ");
+    /// file.extend_from_slice(&16u32.to_le_bytes());
+    /// file.extend_from_slice(&[1, 0, 1, 0, 0x80, 0xbb, 0, 0, 0, 0x77, 1, 0, 2, 0, 16, 0]);
+    /// file.extend_from_slice(b"data");
+    /// file.extend_from_slice(&1000u32.to_le_bytes());
+    /// file.extend_from_slice(&[0; 10]);
+    ///
+    /// assert_eq!(Err("chunk extends past the end of the file"), Wav::parse(&file));
+    /// ```
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, &'static str> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err("not a RIFF/WAVE file");
+        }
+
+        let mut format = None;
+        let mut rest = &bytes[12..];
+        while rest.len() >= 8 {
+            let id = &rest[0..4];
+            let size = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            let chunk = &rest[8..];
+            if size > chunk.len() {
+                return Err("chunk extends past the end of the file");
+            }
+            let body = &chunk[..size];
+
+            match id {
+                b"fmt " => {
+                    if body.len() < 16 {
+                        return Err("fmt chunk too short");
+                    }
+                    let read_u16 = |n: usize| u16::from_le_bytes([body[n], body[n + 1]]);
+                    if read_u16(0) != WAVE_FORMAT_PCM {
+                        return Err("not PCM data");
+                    }
+                    format = Some((
+                        read_u16(2),
+                        u32::from_le_bytes([body[4], body[5], body[6], body[7]]),
+                        read_u16(14),
+                    ));
+                }
+                b"data" => {
+                    let Some((channels, sample_rate, bits_per_sample)) = format else {
+                        return Err("data chunk before fmt chunk");
+                    };
+                    return Ok(Self {
+                        channels,
+                        sample_rate,
+                        bits_per_sample,
+                        data: body,
+                    });
+                }
+                _ => {}
+            }
+
+            // Chunks are padded to an even length, allow the padding to be missing from the last one
+            let next = size.checked_add(size & 1).ok_or("chunk size overflow")?;
+            rest = &chunk[next.min(chunk.len())..];
+        }
+
+        Err("no data chunk")
+    }
+
+    /// The matching [PcmFormat] for decoding the data, if supported
+    pub fn pcm_format(&self) -> Option<PcmFormat> {
+        match self.bits_per_sample {
+            16 => Some(PcmFormat::I16Le),
+            32 => Some(PcmFormat::I32Le),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use alloc::boxed::Box;
+
+use super::{Buffer, Mono};
+use crate::interpolation::Hermite;
+
+/// Circular delay line, backed by a mono buffer
+///
+/// ```
+/// # use owl_patch::sample_buffer::DelayLine;
+/// let mut delay = DelayLine::new(48000);
+///
+/// // per sample, in the audio loop
+/// # let input = 0.5;
+/// let output = delay.read_interpolated(1234.5);
+/// delay.write(input + output * 0.5);
+/// ```
+pub struct DelayLine<F> {
+    buffer: Buffer<Mono, Box<[F]>>,
+    head: usize,
+}
+
+impl<F: Copy + Default> DelayLine<F> {
+    /// Create a new delay line holding `len` samples, all zero (allocates)
+    ///
+    /// The maximum delay that can be read is `len - 1`
+    pub fn new(len: usize) -> Self {
+        assert!(len > 0);
+        Self {
+            buffer: Buffer::new_mono(len),
+            head: 0,
+        }
+    }
+
+    /// Number of samples held
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Always false, delay lines hold at least one sample
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Add a new sample, overwriting the oldest
+    pub fn write(&mut self, sample: F) {
+        self.buffer[self.head] = sample;
+        self.head = (self.head + 1) % self.buffer.len();
+    }
+
+    /// Read a past sample. A delay of 0 is the most recently written sample.
+    ///
+    /// Panics if the delay is not less than [len]
+    /// ```
+    /// # use owl_patch::sample_buffer::DelayLine;
+    /// let mut delay = DelayLine::new(4);
+    /// (1..=6).for_each(|n| delay.write(n));
+    ///
+    /// assert_eq!(6, delay.read(0));
+    /// assert_eq!(5, delay.read(1));
+    /// assert_eq!(3, delay.read(3));
+    /// ```
+    ///
+    /// [len]: DelayLine::len
+    pub fn read(&self, delay: usize) -> F {
+        let len = self.buffer.len();
+        assert!(delay < len);
+        self.buffer[(self.head + len - 1 - delay) % len]
+    }
+
+    /// Clear the delay line to zero
+    pub fn clear(&mut self) {
+        self.buffer.fill(F::default());
+    }
+}
+
+impl DelayLine<f32> {
+    /// Read a past sample at a fractional delay, using [Hermite] interpolation. The delay is clamped to
+    /// (0.0..len - 1)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::DelayLine;
+    /// let mut delay = DelayLine::new(8);
+    /// [0.0, 1.0, 2.0, 3.0, 4.0, 5.0].iter().for_each(|s| delay.write(*s));
+    ///
+    /// assert_eq!(5.0, delay.read_interpolated(0.0));
+    /// assert_eq!(3.0, delay.read_interpolated(2.0));
+    /// assert_eq!(2.5, delay.read_interpolated(2.5));
+    /// assert_eq!(1.75, delay.read_interpolated(3.25));
+    /// ```
+    pub fn read_interpolated(&self, delay: f32) -> f32 {
+        let max = self.buffer.len() - 1;
+        let delay = delay.clamp(0.0, max as f32);
+        let n = delay as usize;
+        let alpha = delay - n as f32;
+
+        f32::hermite(
+            self.read(n.saturating_sub(1)),
+            self.read(n),
+            self.read((n + 1).min(max)),
+            self.read((n + 2).min(max)),
+            alpha,
+        )
+    }
+}
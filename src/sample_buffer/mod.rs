@@ -0,0 +1,1971 @@
+//! Audio Sample Buffers
+
+extern crate alloc;
+
+use core::{
+    marker::PhantomData,
+    ops::{
+        AddAssign, Deref, DerefMut, Div, DivAssign, MulAssign, Neg, RangeBounds, RemAssign,
+        SubAssign,
+    },
+};
+
+use alloc::vec;
+use alloc::{boxed::Box, vec::Vec};
+use num_traits::{MulAddAssign, NumCast, SaturatingAdd, SaturatingSub, WrappingAdd, WrappingSub};
+
+#[cfg(target_os = "none")]
+use num_traits::Float as _;
+
+mod delay_line;
+pub use delay_line::DelayLine;
+
+/// Sample / Buffer conversion trait
+pub trait ConvertFrom<T: ?Sized> {
+    /// Read from `other`, converting into the correct format
+    fn convert_from(&mut self, other: T);
+}
+
+impl<T> ConvertFrom<T> for T {
+    fn convert_from(&mut self, value: T) {
+        *self = value
+    }
+}
+
+/// Sample / Buffer conversion trait
+pub trait ConvertTo<T> {
+    /// Write to `other`, converting into the correct format
+    fn convert_to(&self, other: &mut T);
+}
+
+/// Consuming Sample conversion trait
+pub trait ConvertInto<T> {
+    /// Convert to correct format
+    fn convert_into(self) -> T;
+}
+
+/// Auto implementation making self->self conversion a nop
+impl<T> ConvertInto<T> for T {
+    fn convert_into(self) -> Self {
+        self
+    }
+}
+
+/// Auto implementation
+impl<A, B> ConvertTo<B> for A
+where
+    for<'a> B: ConvertFrom<&'a A>,
+{
+    fn convert_to(&self, other: &mut B) {
+        other.convert_from(self)
+    }
+}
+
+impl ConvertInto<f32> for i32 {
+    /// Convertion to float, so that i32::MAX => 1.0 and i32::MIN => -1.0
+    fn convert_into(self) -> f32 {
+        const MUL: f32 = 1.0 / (0x80000000i64 as f32);
+        self as f32 * MUL
+    }
+}
+
+impl ConvertInto<i32> for f32 {
+    /// Convertion from float, so that 1.0 => i32::MAX and -1.0 => i32::MIN
+    fn convert_into(self) -> i32 {
+        const MUL: f32 = 0x80000000i64 as f32;
+        (self * MUL) as i32
+    }
+}
+
+impl ConvertFrom<i32> for f32 {
+    fn convert_from(&mut self, other: i32) {
+        *self = other.convert_into();
+    }
+}
+
+impl ConvertFrom<f32> for i32 {
+    fn convert_from(&mut self, other: f32) {
+        *self = other.convert_into();
+    }
+}
+
+/// Sample conversion trait which clamps out-of-range values to the limits of the target type
+///
+/// Values above 1.0 (including infinity) map to the maximum value, values below -1.0 map to the minimum,
+/// and NaN maps to 0 (silence).
+/// ```
+/// # use owl_patch::sample_buffer::*;
+/// let mut sample = 0i32;
+///
+/// sample.convert_from_clamped(2.0f32);
+/// assert_eq!(i32::MAX, sample);
+/// sample.convert_from_clamped(f32::NEG_INFINITY);
+/// assert_eq!(i32::MIN, sample);
+/// sample.convert_from_clamped(f32::NAN);
+/// assert_eq!(0, sample);
+/// ```
+pub trait ConvertFromClamped<T> {
+    /// Read from `other`, converting into the correct format and clamping to the valid range
+    fn convert_from_clamped(&mut self, other: T);
+}
+
+impl ConvertFromClamped<f32> for i32 {
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut sample = 0i32;
+    ///
+    /// sample.convert_from_clamped(-2.0f32);
+    /// assert_eq!(i32::MIN, sample);
+    /// sample.convert_from_clamped(f32::INFINITY);
+    /// assert_eq!(i32::MAX, sample);
+    /// sample.convert_from_clamped(0.5f32);
+    /// assert_eq!(0x40000000, sample);
+    /// ```
+    fn convert_from_clamped(&mut self, other: f32) {
+        *self = if other.is_nan() {
+            0
+        } else if other >= 1.0 {
+            i32::MAX
+        } else if other <= -1.0 {
+            i32::MIN
+        } else {
+            other.convert_into()
+        };
+    }
+}
+
+impl ConvertFromClamped<f32> for i16 {
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut sample = 0i16;
+    ///
+    /// sample.convert_from_clamped(2.0f32);
+    /// assert_eq!(i16::MAX, sample);
+    /// sample.convert_from_clamped(-2.0f32);
+    /// assert_eq!(i16::MIN, sample);
+    /// sample.convert_from_clamped(f32::NAN);
+    /// assert_eq!(0, sample);
+    /// sample.convert_from_clamped(f32::NEG_INFINITY);
+    /// assert_eq!(i16::MIN, sample);
+    /// sample.convert_from_clamped(0.5f32);
+    /// assert_eq!(0x4000, sample);
+    /// ```
+    fn convert_from_clamped(&mut self, other: f32) {
+        const MUL: f32 = 0x8000 as f32;
+        *self = if other.is_nan() {
+            0
+        } else if other >= 1.0 {
+            i16::MAX
+        } else if other <= -1.0 {
+            i16::MIN
+        } else {
+            (other * MUL) as i16
+        };
+    }
+}
+
+/// Summary statistics of a block of samples, see [Buffer::stats]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BufferStats {
+    /// Lowest sample value
+    pub min: f32,
+    /// Highest sample value
+    pub max: f32,
+    /// Mean sample value (DC offset)
+    pub mean: f32,
+    /// Root mean square level
+    pub rms: f32,
+}
+
+/// Accessors for a single frame (one sample per channel), as yielded by [Buffer::frames]
+///
+/// ```
+/// # use owl_patch::sample_buffer::*;
+/// let buffer: Buffer<Interleaved, _> = Buffer::new_from(2, 2, vec![0.1, 0.2, 0.3, 0.4]);
+///
+/// for frame in buffer.frames() {
+///     let (left, right) = frame.stereo().unwrap();
+///     assert!(left < right);
+/// }
+/// ```
+pub trait Frame<T> {
+    /// The (left, right) pair, or None if the frame doesn't have exactly 2 channels
+    /// ```
+    /// # use owl_patch::sample_buffer::Frame;
+    /// assert_eq!(Some((&1.0, &2.0)), [1.0, 2.0].stereo());
+    /// assert_eq!(None, [1.0].stereo());
+    /// assert_eq!(None, [1.0, 2.0, 3.0, 4.0].stereo());
+    /// ```
+    fn stereo(&self) -> Option<(&T, &T)>;
+
+    /// The mutable (left, right) pair, or None if the frame doesn't have exactly 2 channels
+    /// ```
+    /// # use owl_patch::sample_buffer::Frame;
+    /// let mut frame = [1.0, 2.0];
+    /// if let Some((left, right)) = frame.stereo_mut() {
+    ///     core::mem::swap(left, right);
+    /// }
+    /// assert_eq!([2.0, 1.0], frame);
+    ///
+    /// assert!([1.0].stereo_mut().is_none());
+    /// ```
+    fn stereo_mut(&mut self) -> Option<(&mut T, &mut T)>;
+}
+
+impl<T> Frame<T> for [T] {
+    fn stereo(&self) -> Option<(&T, &T)> {
+        match self {
+            [left, right] => Some((left, right)),
+            _ => None,
+        }
+    }
+
+    fn stereo_mut(&mut self) -> Option<(&mut T, &mut T)> {
+        match self {
+            [left, right] => Some((left, right)),
+            _ => None,
+        }
+    }
+}
+
+/// Marker trait to indicate how samples are stored in a buffer
+pub trait StoragePattern {}
+
+/// Samples all from a single channel
+pub struct Mono;
+impl StoragePattern for Mono {}
+
+/// Samples stored one channel at a time
+/// eg: `[l0, l1, l2, ..., r0, r1, r2, ...]`
+pub struct Channels;
+impl StoragePattern for Channels {}
+
+/// Samples stored interleaved
+/// eg: `[l0, r0, l1, r1, l2, r2 ...]`
+pub struct Interleaved;
+impl StoragePattern for Interleaved {}
+
+/// Container type for samples
+pub trait Container: AsRef<[Self::Item]> {
+    /// Sample type
+    type Item;
+}
+
+impl<T> Container for [T] {
+    type Item = T;
+}
+
+impl<T> Container for &[T] {
+    type Item = T;
+}
+
+impl<T> Container for &mut [T] {
+    type Item = T;
+}
+
+impl<T> Container for Box<[T]> {
+    type Item = T;
+}
+
+impl<T> Container for Vec<T> {
+    type Item = T;
+}
+
+/// Container type for samples, but it's mutable
+pub trait MutableContainer: Container + AsMut<[Self::Item]> {}
+impl<T> MutableContainer for T where T: Container + AsMut<[Self::Item]> {}
+
+/// Sample buffer
+///
+/// ### Type params:
+///
+/// * S: Describes how the samples are stored, see [StoragePattern]
+/// * C: [Container] or [MutableContainer] type for samples.
+///   * C::Item Can be any of data, but [i32] and [f32] are the ones you'll most likely want to use.
+///     If using [f32], the signal should be kept between -1.0 and 1.0. Anything outside
+///     of this will be clipped during conversion. The range for [i32] is [i32::MIN]..[i32::MAX]
+pub struct Buffer<S: StoragePattern, C: Container> {
+    samples: C,
+    channels: usize,
+    blocksize: usize,
+    _storage: PhantomData<S>,
+}
+
+impl<F: Default + Clone> Buffer<Mono, Box<[F]>> {
+    /// Create a new mono buffer, with owned samples (allocates)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::Buffer;
+    /// let buffer = Buffer::new_mono(4);
+    ///
+    /// assert_eq!(&[0.0f32; 4], buffer.samples());
+    /// ```
+    pub fn new_mono(blocksize: usize) -> Self {
+        let samples = vec![F::default(); blocksize];
+
+        Self {
+            samples: samples.into_boxed_slice(),
+            channels: 1,
+            blocksize,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<F> Buffer<Mono, Box<[F]>> {
+    /// Create a new mono buffer, with owned samples computed from their index (allocates)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::Buffer;
+    /// let ramp = Buffer::from_fn(4, |n| n as f32 / 4.0);
+    ///
+    /// assert_eq!(&[0.0, 0.25, 0.5, 0.75], ramp.samples());
+    /// ```
+    pub fn from_fn(blocksize: usize, f: impl FnMut(usize) -> F) -> Self {
+        (0..blocksize).map(f).collect()
+    }
+}
+
+impl<F> FromIterator<F> for Buffer<Mono, Box<[F]>> {
+    /// Collect samples into a new mono buffer (allocates)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer<Mono, _> = (0..8).map(|n| n as f32).rev().collect();
+    ///
+    /// assert_eq!(8, buffer.len());
+    /// assert_eq!(7.0, buffer[0]);
+    /// ```
+    fn from_iter<I: IntoIterator<Item = F>>(iter: I) -> Self {
+        let samples: Box<[F]> = iter.into_iter().collect();
+        Self {
+            blocksize: samples.len(),
+            samples,
+            channels: 1,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<F: Default + Clone, S: StoragePattern> Buffer<S, Box<[F]>> {
+    /// Create a new buffer, allocating a boxed slice to hold sample data
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// buffer.channels().for_each(|ch| assert_eq!(&[0.0f32; 4], ch.samples()));
+    /// ```
+    pub fn new(channels: usize, blocksize: usize) -> Self {
+        let samples = vec![F::default(); channels * blocksize];
+        Self {
+            samples: samples.into_boxed_slice(),
+            channels,
+            blocksize,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<'a, F, S: StoragePattern> Buffer<S, &'a [F]> {
+    /// Create a new buffer holding a reference to read-only data allocated externally.
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [0.0f32; 8];
+    /// let buffer: Buffer::<Channels, _> = Buffer::new_ref(2, 4, &data);
+    ///
+    /// buffer.channels().for_each(|ch| assert_eq!(&[0.0; 4], ch.samples()));
+    /// ```
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = vec![0.0f32; 8];
+    /// let buffer: Buffer::<Channels, _> = Buffer::new_ref(2, 4, &data);
+    ///
+    /// buffer.channels().for_each(|ch| assert_eq!(&[0.0; 4], ch.samples()));
+    /// ```
+    pub fn new_ref(channels: usize, blocksize: usize, samples: &'a [F]) -> Self {
+        assert_eq!(channels * blocksize, samples.len());
+        Self {
+            samples,
+            channels,
+            blocksize,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<'a, F, S: StoragePattern> Buffer<S, &'a mut [F]> {
+    /// Create a new buffer holding a mutable reference to data allocated externally
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut data = [0.0f32; 8];
+    /// let mut buffer: Buffer::<Channels, _> = Buffer::new_mut(2, 4, &mut data);
+    ///
+    /// buffer += 1.0;
+    /// buffer.channels().for_each(|ch| assert_eq!(&[1.0; 4], ch.samples()));
+    /// ```
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut data = vec![0.0f32; 8];
+    /// let mut buffer: Buffer::<Channels, _> = Buffer::new_mut(2, 4, &mut data);
+    ///
+    /// buffer += 1.0;
+    /// buffer.channels().for_each(|ch| assert_eq!(&[1.0; 4], ch.samples()));
+    /// ```
+    pub fn new_mut(channels: usize, blocksize: usize, samples: &'a mut [F]) -> Self {
+        assert_eq!(channels * blocksize, samples.len());
+        Self {
+            samples,
+            channels,
+            blocksize,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<S: StoragePattern, C: MutableContainer> Buffer<S, C> {
+    /// Create a new buffer from mutable data allocated externally (taking ownership).
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data: Vec<f32> = vec![0.0f32; 8];
+    /// let buffer: Buffer::<Channels, _> = Buffer::new_from(2, 4, data);
+    ///
+    /// buffer.channels().for_each(|ch| assert_eq!(&[0.0; 4], ch.samples()));
+    /// ```
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data: Box<[f32]> = vec![0.0f32; 8].into_boxed_slice();
+    /// let buffer: Buffer::<Interleaved, _> = Buffer::new_from(2, 4, data);
+    ///
+    /// buffer.frames().for_each(|frame| assert_eq!(&[0.0; 2], frame));
+    /// ```
+    pub fn new_from(channels: usize, blocksize: usize, samples: C) -> Self {
+        assert_eq!(channels * blocksize, samples.as_ref().len());
+        Self {
+            samples,
+            channels,
+            blocksize,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<'a, F> Buffer<Mono, &'a [F]> {
+    /// Create a new mono buffer with borrowed samples.
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = vec![0.0f32; 8];
+    /// let buffer = Buffer::mono_ref(&data);
+    ///
+    /// assert_eq!(&[0.0; 8], buffer.samples());
+    /// ```
+    pub fn mono_ref(samples: &'a [F]) -> Self {
+        let len = samples.len();
+        Self {
+            samples,
+            channels: 1,
+            blocksize: len,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<'a, F> Buffer<Mono, &'a mut [F]> {
+    /// Create a new mono buffer with mutably borrowed samples.
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut data: Box<[f32]> = vec![0.0f32; 8].into_boxed_slice();
+    /// let mut buffer = Buffer::mono_mut(&mut data);
+    /// buffer += 1.0;
+    /// assert_eq!(&[1.0; 8], &data.as_ref());
+    /// ```
+    pub fn mono_mut(samples: &'a mut [F]) -> Self {
+        let len = samples.len();
+        Self {
+            samples,
+            channels: 1,
+            blocksize: len,
+            _storage: PhantomData,
+        }
+    }
+}
+
+impl<S: StoragePattern, C: Container> Buffer<S, C> {
+    /// Get a reference to all samples in the buffer.
+    ///
+    /// Whether they are interleaved or not depends on the buffer's type.
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer1: Buffer::<Channels, _> = Buffer::new(2, 2);
+    ///
+    /// buffer1.channels_mut().enumerate().for_each(|(n, mut ch)| ch += n as f32);
+    /// assert_eq!(&[0.0f32, 0.0, 1.0, 1.0], buffer1.samples());
+    ///
+    /// let mut buffer2: Buffer::<Interleaved, _> = Buffer::new(2, 2);
+    /// buffer2.convert_from(&buffer1);
+    ///
+    /// assert_eq!(&[0.0f32, 1.0, 0.0, 1.0], buffer2.samples());
+    /// ```
+    pub fn samples(&self) -> &[C::Item] {
+        self.samples.as_ref()
+    }
+}
+
+impl<S: StoragePattern, C: MutableContainer> Buffer<S, C> {
+    /// Get a mutable reference to all samples in the buffer.
+    ///
+    /// Whether they are interleaved or not depends on the buffer's type.
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer::<Channels, _> = Buffer::new(2, 2);
+    ///
+    /// buffer.samples_mut().copy_from_slice(&[0.0, 1.0, 2.0, 3.0]);
+    ///
+    /// assert_eq!(&[0.0, 1.0], buffer.left().unwrap().samples());
+    /// assert_eq!(&[2.0, 3.0], buffer.right().unwrap().samples());
+    /// ```
+    pub fn samples_mut(&mut self) -> &mut [C::Item] {
+        self.samples.as_mut()
+    }
+}
+
+impl<C: Container> Buffer<Mono, C> {
+    /// Borrow a range of samples as a new buffer, without copying
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer = Buffer::from_fn(8, |n| n as f32);
+    /// let window = buffer.slice(2..5);
+    ///
+    /// assert_eq!(3, window.len());
+    /// assert_eq!(&[2.0, 3.0, 4.0], window.samples());
+    /// assert_eq!(&[6.0, 7.0], buffer.slice(6..).samples());
+    /// ```
+    ///
+    /// Panics if the range is out of bounds
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Buffer<Mono, &[C::Item]> {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        Buffer::mono_ref(&self.samples()[range])
+    }
+
+    /// Iterate over the samples in borrowed buffers of `size` samples. The last may be shorter
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let sample = Buffer::from_fn(100, |n| n as f32);
+    /// let chunks: Vec<_> = sample.chunks(32).collect();
+    ///
+    /// assert_eq!(4, chunks.len());
+    /// assert_eq!(32, chunks[0].len());
+    /// assert_eq!(4, chunks[3].len());
+    /// assert_eq!(96.0, chunks[3][0]);
+    /// ```
+    ///
+    /// Panics if `size` is 0
+    pub fn chunks(&self, size: usize) -> impl Iterator<Item = Buffer<Mono, &[C::Item]>> {
+        self.samples().chunks(size).map(Buffer::mono_ref)
+    }
+}
+
+impl<C: MutableContainer> Buffer<Mono, C> {
+    /// Mutably borrow a range of samples as a new buffer, without copying
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer<Mono, Box<[f32]>> = Buffer::new_mono(6);
+    ///
+    /// let mut window = buffer.slice_mut(1..3);
+    /// window += 1.0;
+    ///
+    /// assert_eq!(&[0.0, 1.0, 1.0, 0.0, 0.0, 0.0], buffer.samples());
+    /// ```
+    ///
+    /// Panics if the range is out of bounds
+    pub fn slice_mut(&mut self, range: impl RangeBounds<usize>) -> Buffer<Mono, &mut [C::Item]> {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        Buffer::mono_mut(&mut self.samples_mut()[range])
+    }
+
+    /// Iterate over the samples in mutably borrowed buffers of `size` samples. The last may be shorter
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut sample: Buffer<Mono, Box<[f32]>> = Buffer::new_mono(10);
+    /// for (n, mut chunk) in sample.chunks_mut(4).enumerate() {
+    ///     chunk += n as f32;
+    /// }
+    ///
+    /// assert_eq!(&[0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 2.0, 2.0], sample.samples());
+    /// ```
+    ///
+    /// Panics if `size` is 0
+    pub fn chunks_mut(
+        &mut self,
+        size: usize,
+    ) -> impl Iterator<Item = Buffer<Mono, &mut [C::Item]>> {
+        self.samples_mut().chunks_mut(size).map(Buffer::mono_mut)
+    }
+}
+
+#[doc(hidden)]
+impl<C: Container> Deref for Buffer<Mono, C> {
+    type Target = [C::Item];
+
+    fn deref(&self) -> &Self::Target {
+        self.samples()
+    }
+}
+
+#[doc(hidden)]
+impl<C: MutableContainer> DerefMut for Buffer<Mono, C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.samples_mut()
+    }
+}
+
+impl<C: Container<Item = f32>> Buffer<Mono, C> {
+    /// Resample into a new buffer, reading the source at `ratio` times the original speed using linear
+    /// interpolation. A ratio of 2.0 halves the length (up an octave), 0.5 doubles it.
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let ramp: Vec<f32> = (0..8).map(|n| n as f32).collect();
+    /// let buffer = Buffer::mono_ref(&ramp);
+    ///
+    /// let faster = buffer.resample(2.0);
+    /// assert_eq!(&[0.0, 2.0, 4.0, 6.0], faster.samples());
+    ///
+    /// let slower = buffer.resample(0.5);
+    /// assert_eq!(16, slower.len());
+    /// slower.iter().enumerate().for_each(|(n, s)| assert!((s - (n as f32 * 0.5).min(7.0)).abs() < 1e-6));
+    /// ```
+    pub fn resample(&self, ratio: f32) -> Buffer<Mono, Box<[f32]>> {
+        assert!(ratio > 0.0);
+        let mut output = Buffer::new_mono((self.blocksize as f32 / ratio) as usize);
+        self.resample_at(&mut output, ratio);
+        output
+    }
+
+    /// Resample to fill an existing buffer, stretching or squashing the samples to fit
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let ramp = [0.0f32, 1.0, 2.0, 3.0];
+    /// let mut output = Buffer::new_mono(8);
+    ///
+    /// Buffer::mono_ref(&ramp).resample_into(&mut output);
+    /// assert_eq!(&[0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.0], output.samples());
+    /// ```
+    pub fn resample_into<C2: MutableContainer<Item = f32>>(&self, output: &mut Buffer<Mono, C2>) {
+        let ratio = self.blocksize as f32 / output.blocksize as f32;
+        self.resample_at(output, ratio);
+    }
+
+    /// Root mean square level of the samples. An empty buffer has a level of 0.0
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let sine: Vec<f32> = (0..64)
+    ///     .map(|n| 0.5 * (n as f32 * core::f32::consts::TAU / 64.0).sin())
+    ///     .collect();
+    ///
+    /// let rms = Buffer::mono_ref(&sine).rms();
+    /// assert!((rms - 0.5 * core::f32::consts::FRAC_1_SQRT_2).abs() < 1e-5);
+    /// ```
+    pub fn rms(&self) -> f32 {
+        if self.blocksize == 0 {
+            return 0.0;
+        }
+        (self.iter().map(|s| s * s).sum::<f32>() / self.blocksize as f32).sqrt()
+    }
+
+    /// Peak absolute level of the samples
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let sine: Vec<f32> = (0..64)
+    ///     .map(|n| 0.5 * (n as f32 * core::f32::consts::TAU / 64.0).sin())
+    ///     .collect();
+    ///
+    /// assert_eq!(0.5, Buffer::mono_ref(&sine).peak());
+    /// ```
+    pub fn peak(&self) -> f32 {
+        self.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+    }
+
+    /// Min, max, mean and rms level of the samples, calculated in a single pass. An empty buffer gives all 0.0
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [-0.5f32, 1.0, 0.5, -1.0, 0.25, 0.75, 0.0, 1.0];
+    /// let stats = Buffer::mono_ref(&data).stats();
+    ///
+    /// assert_eq!(-1.0, stats.min);
+    /// assert_eq!(1.0, stats.max);
+    /// assert_eq!(0.25, stats.mean);
+    /// assert!((stats.rms - (4.125f32 / 8.0).sqrt()).abs() < 1e-6);
+    /// ```
+    pub fn stats(&self) -> BufferStats {
+        if self.blocksize == 0 {
+            return BufferStats::default();
+        }
+
+        let (min, max, sum, sum_squares) = self.iter().fold(
+            (f32::INFINITY, f32::NEG_INFINITY, 0.0, 0.0),
+            |(min, max, sum, sum_squares), &s| {
+                (min.min(s), max.max(s), sum + s, sum_squares + s * s)
+            },
+        );
+
+        BufferStats {
+            min,
+            max,
+            mean: sum / self.blocksize as f32,
+            rms: (sum_squares / self.blocksize as f32).sqrt(),
+        }
+    }
+
+    fn resample_at<C2: MutableContainer<Item = f32>>(
+        &self,
+        output: &mut Buffer<Mono, C2>,
+        ratio: f32,
+    ) {
+        let samples = self.samples();
+        if samples.is_empty() {
+            output.fill(0.0);
+            return;
+        }
+
+        for (n, o) in output.iter_mut().enumerate() {
+            let position = n as f32 * ratio;
+            let index = (position as usize).min(samples.len() - 1);
+            let alpha = position - index as f32;
+            let a = samples[index];
+            let b = samples.get(index + 1).copied().unwrap_or(a);
+            *o = a + (b - a) * alpha;
+        }
+    }
+}
+
+impl<C: Container> Buffer<Interleaved, C> {
+    /// Get an iterator over the samples for each frame
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer::<Interleaved, _> = Buffer::new(2, 32);
+    ///
+    /// buffer.frames().for_each(|frame| assert_eq!(&[0.0; 2], frame));
+    /// ```
+    pub fn frames(&self) -> impl Iterator<Item = &[C::Item]> {
+        self.samples.as_ref().chunks_exact(self.channels)
+    }
+}
+impl<C: MutableContainer> Buffer<Interleaved, C> {
+    /// Get a mutable iterator over the samples for each frame
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer::<Interleaved, _> = Buffer::new(2, 2);
+    ///
+    /// buffer.frames_mut().for_each(|frame| frame.copy_from_slice(&[1.0, 2.0]));
+    ///
+    /// assert_eq!(&[1.0f32, 2.0, 1.0, 2.0], buffer.samples());
+    /// ```
+    pub fn frames_mut(&mut self) -> impl Iterator<Item = &mut [C::Item]> {
+        self.samples.as_mut().chunks_exact_mut(self.channels)
+    }
+}
+
+impl<C: Container> Buffer<Channels, C> {
+    /// Get an iterator over the samples for each channel
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// buffer.channels().for_each(|ch| assert_eq!(&[0.0; 4], ch.samples()));
+    /// ```
+    pub fn channels(&self) -> impl Iterator<Item = Buffer<Mono, &[C::Item]>> {
+        self.samples
+            .as_ref()
+            .chunks(self.blocksize)
+            .map(Buffer::mono_ref)
+    }
+
+    /// Get the samples in the left channel as a readonly mono buffer
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// assert!(buffer.left().is_some());
+    /// if let Some(left) = buffer.left() {
+    ///     assert_eq!(&[0.0; 4], left.samples())
+    /// }
+    /// ```
+    pub fn left(&self) -> Option<Buffer<Mono, &[C::Item]>> {
+        self.channels().take(1).next()
+    }
+
+    /// Get the samples in the right channel as a readonly mono buffer
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// assert!(buffer.right().is_some());
+    /// if let Some(right) = buffer.right() {
+    ///     assert_eq!(&[0.0; 4], right.samples())
+    /// }
+    /// ```
+    ///
+    /// None will be returned if the buffer doesn't have at least 2 channels
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer::<Channels, Box<[f32]>> = Buffer::new(1, 4);
+    ///
+    /// assert!(buffer.right().is_none());
+    /// ```
+    pub fn right(&self) -> Option<Buffer<Mono, &[C::Item]>> {
+        self.channels().skip(1).take(1).next()
+    }
+}
+
+impl<C: Container<Item = f32>> Buffer<Channels, C> {
+    /// Root mean square level of each channel
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [1.0f32, -1.0, 1.0, -1.0, 0.0, 0.0, 0.0, 0.0];
+    /// let buffer: Buffer<Channels, _> = Buffer::new_ref(2, 4, &data);
+    ///
+    /// assert!(buffer.rms().eq([1.0, 0.0]));
+    /// ```
+    pub fn rms(&self) -> impl Iterator<Item = f32> + '_ {
+        self.channels().map(|ch| ch.rms())
+    }
+
+    /// Peak absolute level of each channel
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [0.1f32, -0.8, 0.2, 0.0, 0.5, 0.3, -0.1, 0.0];
+    /// let buffer: Buffer<Channels, _> = Buffer::new_ref(2, 4, &data);
+    ///
+    /// assert!(buffer.peak().eq([0.8, 0.5]));
+    /// ```
+    pub fn peak(&self) -> impl Iterator<Item = f32> + '_ {
+        self.channels().map(|ch| ch.peak())
+    }
+
+    /// Statistics for each channel, see [Buffer::<Mono, _>::stats]
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [0.1f32, -0.8, 0.2, 0.5, 0.5, 0.5, 0.5, 0.5];
+    /// let buffer: Buffer<Channels, _> = Buffer::new_ref(2, 4, &data);
+    /// let stats: Vec<_> = buffer.stats().collect();
+    ///
+    /// assert_eq!(-0.8, stats[0].min);
+    /// assert_eq!(0.5, stats[0].max);
+    /// assert_eq!(BufferStats { min: 0.5, max: 0.5, mean: 0.5, rms: 0.5 }, stats[1]);
+    /// ```
+    pub fn stats(&self) -> impl Iterator<Item = BufferStats> + '_ {
+        self.channels().map(|ch| ch.stats())
+    }
+}
+
+impl<C: MutableContainer> Buffer<Channels, C> {
+    /// Get a mutable iterator over the samples for each channel
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer::<Channels, _> = Buffer::new(2, 2);
+    ///
+    /// buffer.channels_mut().enumerate().for_each(|(n, mut ch)| ch += n as f32);
+    /// assert_eq!(&[0.0f32, 0.0, 1.0, 1.0], buffer.samples());
+    /// ```
+    pub fn channels_mut(&mut self) -> impl Iterator<Item = Buffer<Mono, &mut [C::Item]>> {
+        self.samples
+            .as_mut()
+            .chunks_mut(self.blocksize)
+            .map(Buffer::mono_mut)
+    }
+
+    /// Get the samples in the left channel as a mutable mono buffer
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// if let Some(mut left) = buffer.left_mut() {
+    ///     left.samples_mut().fill(1.0);
+    /// }
+    /// assert_eq!(&[1.0f32, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0], buffer.samples());
+    /// ```
+    pub fn left_mut(&mut self) -> Option<Buffer<Mono, &mut [C::Item]>> {
+        self.channels_mut().take(1).next()
+    }
+
+    /// Get the samples in the right channel as a mutable mono buffer
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// if let Some(mut right) = buffer.right_mut() {
+    ///     right.samples_mut().fill(1.0);
+    /// }
+    /// assert_eq!(&[0.0f32, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0,], buffer.samples());
+    /// ```
+    pub fn right_mut(&mut self) -> Option<Buffer<Mono, &mut [C::Item]>> {
+        self.channels_mut().skip(1).take(1).next()
+    }
+}
+
+impl<F2, S, C> ConvertFrom<&[F2]> for Buffer<S, C>
+where
+    C: MutableContainer,
+    C::Item: ConvertFrom<F2>,
+    S: StoragePattern,
+    F2: Copy,
+{
+    /// Convert from a slice of data - the storage type is assumed to be the same
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [i32::MIN, 0, i32::MAX, 0, i32::MIN, 0, i32::MAX, 0];
+    /// let mut buffer: Buffer::<Channels, Box<[f32]>> = Buffer::new(2, 4);
+    /// buffer.convert_from(&data[..]);
+    ///
+    /// assert_eq!(&[-1.0, 0.0, 1.0, 0.0, -1.0, 0.0, 1.0, 0.0], buffer.samples());
+    /// ```
+    fn convert_from(&mut self, other: &[F2]) {
+        assert_eq!(self.samples.as_ref().len(), other.len());
+        for (o, i) in self.samples.as_mut().iter_mut().zip(other.iter()) {
+            o.convert_from(*i);
+        }
+    }
+}
+
+impl<S, C1, C2> ConvertFrom<&Buffer<S, C2>> for Buffer<S, C1>
+where
+    S: StoragePattern,
+    C1::Item: ConvertFrom<C2::Item>,
+    C2::Item: Copy,
+    C1: MutableContainer,
+    C2: Container,
+{
+    /// Convert from another buffer with the same storage type (but maybe different sample format)
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [i32::MIN, 0, i32::MAX, 0, i32::MIN, 0, i32::MAX, 0];
+    /// let buffer1: Buffer::<Channels, _> = Buffer::new_ref(2, 4, &data);
+    /// let mut buffer2: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// buffer2.convert_from(&buffer1);
+    ///
+    /// assert_eq!(&[-1.0f32, 0.0, 1.0, 0.0, -1.0, 0.0, 1.0, 0.0], buffer2.samples());
+    /// ```
+    fn convert_from(&mut self, other: &Buffer<S, C2>) {
+        self.convert_from(other.samples.as_ref());
+    }
+}
+
+impl<F, S, C> ConvertFrom<&Buffer<S, C>> for &mut [F]
+where
+    C::Item: Copy,
+    C: Container,
+    S: StoragePattern,
+    F: ConvertFrom<C::Item>,
+{
+    /// Convert to a slice of data
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    ///
+    /// let buffer: Buffer::<Channels, _> = Buffer::new_from(2, 4, vec![-1.0, 0.0, 1.0, 0.0, -1.0, 0.0, 1.0, 0.0]);
+    /// let mut output = [0i32; 8];
+    /// output.as_mut_slice().convert_from(&buffer);
+    ///
+    /// assert_eq!([i32::MIN, 0, i32::MAX, 0, i32::MIN, 0, i32::MAX, 0], output);
+    /// ```
+    fn convert_from(&mut self, other: &Buffer<S, C>) {
+        assert_eq!(self.len(), other.samples.as_ref().len());
+        for (o, i) in self.iter_mut().zip(other.samples.as_ref().iter()) {
+            o.convert_from(*i);
+        }
+    }
+}
+
+//
+impl<C1, C2> ConvertFrom<&Buffer<Channels, C2>> for Buffer<Interleaved, C1>
+where
+    C1: MutableContainer,
+    C2: Container,
+    C1::Item: ConvertFrom<C2::Item>,
+    C2::Item: Copy,
+{
+    /// Convert from Channels to Interleaved
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    ///
+    /// let buffer1: Buffer::<Channels, _> = Buffer::new_from(2, 4, vec![-1.0, 0.0, 1.0, 0.0, -1.0, 0.0, 1.0, 0.0]);
+    /// let mut buffer2: Buffer::<Interleaved, _> = Buffer::new(2, 4);
+    ///
+    /// buffer2.convert_from(&buffer1);
+    ///
+    /// assert_eq!(&[i32::MIN, i32::MIN, 0, 0, i32::MAX, i32::MAX, 0, 0], buffer2.samples());
+    /// ```
+    fn convert_from(&mut self, other: &Buffer<Channels, C2>) {
+        assert_eq!(self.channels, other.channels);
+        for (n, ch) in other.channels().enumerate() {
+            self.samples
+                .as_mut()
+                .iter_mut()
+                .skip(n)
+                .step_by(self.channels)
+                .zip(ch.samples())
+                .for_each(|(s, os)| s.convert_from(*os));
+        }
+    }
+}
+
+// Interleaved -> Channels
+impl<C1, C2> ConvertFrom<&Buffer<Interleaved, C2>> for Buffer<Channels, C1>
+where
+    C1: MutableContainer,
+    C2: Container,
+    C1::Item: ConvertFrom<C2::Item>,
+    C2::Item: Copy,
+{
+    /// Convert from Interleaved to Channels
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    ///
+    /// let buffer1: Buffer::<Interleaved, _> = Buffer::new_from(2, 4, vec![-1.0, -1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0]);
+    /// let mut buffer2: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// buffer2.convert_from(&buffer1);
+    ///
+    /// assert_eq!(&[i32::MIN, 0, i32::MAX, 0, i32::MIN, 0, i32::MAX, 0], buffer2.samples());
+    /// ```
+    fn convert_from(&mut self, other: &Buffer<Interleaved, C2>) {
+        assert_eq!(self.channels, other.channels);
+        for (n, mut ch) in self.channels_mut().enumerate() {
+            other
+                .samples
+                .as_ref()
+                .iter()
+                .skip(n)
+                .step_by(other.channels)
+                .zip(ch.samples_mut())
+                .for_each(|(os, s)| s.convert_from(*os));
+        }
+    }
+}
+
+impl<F: Copy, C: Container<Item = F>> Buffer<Interleaved, C> {
+    /// Copy the samples into an existing channel-layout buffer, without converting or allocating. The same as
+    /// [ConvertFrom::convert_from] with matching sample types, but also checks the blocksize.
+    ///
+    /// Panics if the number of channels or blocksize differ
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [1.0f32, -1.0, 2.0, -2.0, 3.0, -3.0, 4.0, -4.0];
+    /// let interleaved: Buffer<Interleaved, _> = Buffer::new_ref(2, 4, &data);
+    ///
+    /// let mut output = [0.0f32; 8];
+    /// let mut channels: Buffer<Channels, _> = Buffer::new_mut(2, 4, &mut output);
+    /// interleaved.deinterleave_into(&mut channels);
+    ///
+    /// assert_eq!(&[1.0, 2.0, 3.0, 4.0], channels.left().unwrap().samples());
+    /// assert_eq!(&[-1.0, -2.0, -3.0, -4.0], channels.right().unwrap().samples());
+    /// ```
+    pub fn deinterleave_into<C2: MutableContainer<Item = F>>(
+        &self,
+        dest: &mut Buffer<Channels, C2>,
+    ) {
+        assert_eq!(self.blocksize, dest.blocksize);
+        dest.convert_from(self);
+    }
+
+    /// Copy the samples into a new channel-layout buffer (allocates)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let interleaved: Buffer<Interleaved, _> = Buffer::new_from(2, 3, vec![1.0f32, -1.0, 2.0, -2.0, 3.0, -3.0]);
+    /// let channels = interleaved.to_channels();
+    ///
+    /// assert_eq!(&[1.0, 2.0, 3.0, -1.0, -2.0, -3.0], channels.samples());
+    /// assert_eq!(interleaved.samples(), channels.to_interleaved().samples());
+    /// ```
+    pub fn to_channels(&self) -> Buffer<Channels, Box<[F]>>
+    where
+        F: Default,
+    {
+        let mut dest = Buffer::new(self.channels, self.blocksize);
+        self.deinterleave_into(&mut dest);
+        dest
+    }
+}
+
+impl<F: Copy, C: Container<Item = F>> Buffer<Channels, C> {
+    /// Copy the samples into an existing interleaved buffer, without converting or allocating. The same as
+    /// [ConvertFrom::convert_from] with matching sample types, but also checks the blocksize.
+    ///
+    /// Panics if the number of channels or blocksize differ
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data = [1.0f32, 2.0, 3.0, 4.0, -1.0, -2.0, -3.0, -4.0];
+    /// let channels: Buffer<Channels, _> = Buffer::new_ref(2, 4, &data);
+    ///
+    /// let mut output = [0.0f32; 8];
+    /// let mut interleaved: Buffer<Interleaved, _> = Buffer::new_mut(2, 4, &mut output);
+    /// channels.interleave_into(&mut interleaved);
+    ///
+    /// assert_eq!(&[1.0, -1.0, 2.0, -2.0, 3.0, -3.0, 4.0, -4.0], interleaved.samples());
+    /// ```
+    pub fn interleave_into<C2: MutableContainer<Item = F>>(
+        &self,
+        dest: &mut Buffer<Interleaved, C2>,
+    ) {
+        assert_eq!(self.blocksize, dest.blocksize);
+        dest.convert_from(self);
+    }
+
+    /// Copy the samples into a new interleaved buffer (allocates)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let channels: Buffer<Channels, _> = Buffer::new_from(2, 2, vec![1, 2, 3, 4]);
+    ///
+    /// assert_eq!(&[1, 3, 2, 4], channels.to_interleaved().samples());
+    /// ```
+    pub fn to_interleaved(&self) -> Buffer<Interleaved, Box<[F]>>
+    where
+        F: Default,
+    {
+        let mut dest = Buffer::new(self.channels, self.blocksize);
+        self.interleave_into(&mut dest);
+        dest
+    }
+}
+
+/// How a mono signal is spread across the channels of a multi-channel buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpmixPolicy {
+    /// Copy the mono signal into every channel
+    Duplicate,
+    /// Copy the mono signal into the first channel only, the others are silenced
+    FirstChannel,
+}
+
+/// How the channels of a multi-channel buffer are combined into a mono signal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DownmixPolicy {
+    /// Average of all channels
+    Average,
+    /// Sum of all channels. Integer samples will wrap on overflow
+    Sum,
+    /// Take the first channel only, ignoring the others
+    FirstChannel,
+}
+
+impl<F, C> Buffer<Channels, C>
+where
+    F: Copy + Default,
+    C: MutableContainer<Item = F>,
+{
+    /// Fill every channel from a mono buffer of the same blocksize (eg: mono synth -> stereo output)
+    ///
+    /// Conversion in the other direction is done with [downmix_to]
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mono = Buffer::mono_ref(&[1.0f32, 2.0, 3.0, 4.0]);
+    /// let mut buffer: Buffer::<Channels, _> = Buffer::new(2, 4);
+    ///
+    /// buffer.upmix_from(&mono, UpmixPolicy::Duplicate);
+    /// assert_eq!(&[1.0, 2.0, 3.0, 4.0, 1.0, 2.0, 3.0, 4.0], buffer.samples());
+    ///
+    /// buffer.upmix_from(&mono, UpmixPolicy::FirstChannel);
+    /// assert_eq!(&[1.0, 2.0, 3.0, 4.0, 0.0, 0.0, 0.0, 0.0], buffer.samples());
+    /// ```
+    ///
+    /// [downmix_to]: Buffer::downmix_to
+    pub fn upmix_from<C2: Container<Item = F>>(
+        &mut self,
+        mono: &Buffer<Mono, C2>,
+        policy: UpmixPolicy,
+    ) {
+        assert_eq!(self.blocksize, mono.blocksize);
+        for (n, mut ch) in self.channels_mut().enumerate() {
+            match policy {
+                UpmixPolicy::FirstChannel if n > 0 => ch.fill(F::default()),
+                _ => ch.copy_from_slice(mono.samples()),
+            }
+        }
+    }
+}
+
+impl<F, C> Buffer<Channels, C>
+where
+    F: Copy + Default + AddAssign + Div<Output = F> + NumCast,
+    C: Container<Item = F>,
+{
+    /// Mix all channels down into a mono buffer of the same blocksize
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer::<Channels, _> = Buffer::new_from(2, 4, vec![1.0f32, 2.0, 3.0, 4.0, 3.0, 4.0, 5.0, 6.0]);
+    /// let mut mono = Buffer::new_mono(4);
+    ///
+    /// buffer.downmix_to(&mut mono, DownmixPolicy::Average);
+    /// assert_eq!(&[2.0, 3.0, 4.0, 5.0], mono.samples());
+    ///
+    /// buffer.downmix_to(&mut mono, DownmixPolicy::Sum);
+    /// assert_eq!(&[4.0, 6.0, 8.0, 10.0], mono.samples());
+    ///
+    /// buffer.downmix_to(&mut mono, DownmixPolicy::FirstChannel);
+    /// assert_eq!(&[1.0, 2.0, 3.0, 4.0], mono.samples());
+    /// ```
+    pub fn downmix_to<C2: MutableContainer<Item = F>>(
+        &self,
+        mono: &mut Buffer<Mono, C2>,
+        policy: DownmixPolicy,
+    ) {
+        assert_eq!(self.blocksize, mono.blocksize);
+        let divisor = F::from(self.channels).expect("channel count out of range");
+        mono.fill(F::default());
+
+        for (n, ch) in self.channels().enumerate() {
+            for (o, i) in mono.iter_mut().zip(ch.iter()) {
+                match policy {
+                    DownmixPolicy::Average => *o += *i / divisor,
+                    DownmixPolicy::Sum => *o += *i,
+                    DownmixPolicy::FirstChannel if n == 0 => *o = *i,
+                    DownmixPolicy::FirstChannel => {}
+                }
+            }
+        }
+    }
+}
+
+impl<F, C> Buffer<Interleaved, C>
+where
+    F: Copy + Default,
+    C: MutableContainer<Item = F>,
+{
+    /// Fill every channel from a mono buffer of the same blocksize (eg: mono synth -> stereo output)
+    ///
+    /// Conversion in the other direction is done with [downmix_to]
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mono = Buffer::mono_ref(&[1.0f32, 2.0, 3.0, 4.0]);
+    /// let mut buffer: Buffer::<Interleaved, _> = Buffer::new(2, 4);
+    ///
+    /// buffer.upmix_from(&mono, UpmixPolicy::Duplicate);
+    /// assert_eq!(&[1.0, 1.0, 2.0, 2.0, 3.0, 3.0, 4.0, 4.0], buffer.samples());
+    ///
+    /// buffer.upmix_from(&mono, UpmixPolicy::FirstChannel);
+    /// assert_eq!(&[1.0, 0.0, 2.0, 0.0, 3.0, 0.0, 4.0, 0.0], buffer.samples());
+    /// ```
+    ///
+    /// [downmix_to]: Buffer::downmix_to
+    pub fn upmix_from<C2: Container<Item = F>>(
+        &mut self,
+        mono: &Buffer<Mono, C2>,
+        policy: UpmixPolicy,
+    ) {
+        assert_eq!(self.blocksize, mono.blocksize);
+        for (frame, s) in self.frames_mut().zip(mono.iter()) {
+            match policy {
+                UpmixPolicy::Duplicate => frame.fill(*s),
+                UpmixPolicy::FirstChannel => {
+                    frame.fill(F::default());
+                    frame[0] = *s;
+                }
+            }
+        }
+    }
+}
+
+impl<F, C> Buffer<Interleaved, C>
+where
+    F: Copy + Default + AddAssign + Div<Output = F> + NumCast,
+    C: Container<Item = F>,
+{
+    /// Mix all channels down into a mono buffer of the same blocksize
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let buffer: Buffer::<Interleaved, _> = Buffer::new_from(2, 4, vec![1.0f32, 3.0, 2.0, 4.0, 3.0, 5.0, 4.0, 6.0]);
+    /// let mut mono = Buffer::new_mono(4);
+    ///
+    /// buffer.downmix_to(&mut mono, DownmixPolicy::Average);
+    /// assert_eq!(&[2.0, 3.0, 4.0, 5.0], mono.samples());
+    ///
+    /// buffer.downmix_to(&mut mono, DownmixPolicy::Sum);
+    /// assert_eq!(&[4.0, 6.0, 8.0, 10.0], mono.samples());
+    ///
+    /// buffer.downmix_to(&mut mono, DownmixPolicy::FirstChannel);
+    /// assert_eq!(&[1.0, 2.0, 3.0, 4.0], mono.samples());
+    /// ```
+    pub fn downmix_to<C2: MutableContainer<Item = F>>(
+        &self,
+        mono: &mut Buffer<Mono, C2>,
+        policy: DownmixPolicy,
+    ) {
+        assert_eq!(self.blocksize, mono.blocksize);
+        let divisor = F::from(self.channels).expect("channel count out of range");
+
+        for (o, frame) in mono.iter_mut().zip(self.frames()) {
+            *o = match policy {
+                DownmixPolicy::Average => frame.iter().fold(F::default(), |mut acc, s| {
+                    acc += *s / divisor;
+                    acc
+                }),
+                DownmixPolicy::Sum => frame.iter().fold(F::default(), |mut acc, s| {
+                    acc += *s;
+                    acc
+                }),
+                DownmixPolicy::FirstChannel => frame[0],
+            }
+        }
+    }
+}
+
+/// Linear ramp from `from` (first frame) to `to` (last frame)
+fn ramp(from: f32, to: f32, frames: usize) -> impl Iterator<Item = f32> {
+    let step = if frames > 1 {
+        (to - from) / (frames - 1) as f32
+    } else {
+        0.0
+    };
+    (0..frames).map(move |n| from + step * n as f32)
+}
+
+impl<C: MutableContainer<Item = f32>> Buffer<Mono, C> {
+    /// Apply a gain which changes linearly across the block, from `from` on the first sample to `to` on the
+    /// last. Avoids the zipper noise caused by jumping between gains at block boundaries.
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer = Buffer::new_mono(5);
+    /// buffer.fill(1.0);
+    ///
+    /// buffer.apply_gain_ramp(0.0, 1.0);
+    /// assert_eq!(&[0.0, 0.25, 0.5, 0.75, 1.0], buffer.samples());
+    /// ```
+    pub fn apply_gain_ramp(&mut self, from: f32, to: f32) {
+        let gains = ramp(from, to, self.blocksize);
+        self.iter_mut().zip(gains).for_each(|(s, gain)| *s *= gain);
+    }
+
+    /// Mix in `other`, with the mix changing linearly across the block from `mix_start` on the first sample
+    /// to `mix_end` on the last. A mix of 0.0 leaves `self` unchanged, 1.0 replaces it with `other`.
+    ///
+    /// Panics if the buffers are different sizes
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer = Buffer::new_mono(3);
+    /// buffer.fill(1.0);
+    /// let mut other = Buffer::new_mono(3);
+    /// other.fill(-1.0);
+    ///
+    /// buffer.crossfade(&other, 0.0, 1.0);
+    /// assert_eq!(&[1.0, 0.0, -1.0], buffer.samples());
+    /// ```
+    pub fn crossfade<C2: Container<Item = f32>>(
+        &mut self,
+        other: &Buffer<Mono, C2>,
+        mix_start: f32,
+        mix_end: f32,
+    ) {
+        assert_eq!(self.blocksize, other.blocksize);
+        let mixes = ramp(mix_start, mix_end, self.blocksize);
+        self.iter_mut()
+            .zip(other.iter())
+            .zip(mixes)
+            .for_each(|((s, o), mix)| *s += (o - *s) * mix);
+    }
+}
+
+impl<C: MutableContainer<Item = f32>> Buffer<Channels, C> {
+    /// Apply a gain which changes linearly across the block, from `from` on the first frame to `to` on the
+    /// last. See [Buffer::apply_gain_ramp](Buffer<Mono, C>::apply_gain_ramp)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer<Channels, _> = Buffer::new(2, 3);
+    /// buffer += 1.0;
+    ///
+    /// buffer.apply_gain_ramp(1.0, 0.0);
+    /// assert_eq!(&[1.0, 0.5, 0.0, 1.0, 0.5, 0.0], buffer.samples());
+    /// ```
+    pub fn apply_gain_ramp(&mut self, from: f32, to: f32) {
+        self.channels_mut()
+            .for_each(|mut ch| ch.apply_gain_ramp(from, to));
+    }
+
+    /// Mix in `other`, with the mix changing linearly across the block. See
+    /// [Buffer::crossfade](Buffer<Mono, C>::crossfade)
+    ///
+    /// Panics if the buffers are different sizes
+    pub fn crossfade<C2: Container<Item = f32>>(
+        &mut self,
+        other: &Buffer<Channels, C2>,
+        mix_start: f32,
+        mix_end: f32,
+    ) {
+        assert_eq!(self.channels, other.channels);
+        self.channels_mut()
+            .zip(other.channels())
+            .for_each(|(mut ch, o)| ch.crossfade(&o, mix_start, mix_end));
+    }
+}
+
+impl<C: MutableContainer<Item = f32>> Buffer<Interleaved, C> {
+    /// Apply a gain which changes linearly across the block, from `from` on the first frame to `to` on the
+    /// last. See [Buffer::apply_gain_ramp](Buffer<Mono, C>::apply_gain_ramp)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer<Interleaved, _> = Buffer::new(2, 3);
+    /// buffer += 1.0;
+    ///
+    /// buffer.apply_gain_ramp(1.0, 0.0);
+    /// assert_eq!(&[1.0, 1.0, 0.5, 0.5, 0.0, 0.0], buffer.samples());
+    /// ```
+    pub fn apply_gain_ramp(&mut self, from: f32, to: f32) {
+        let blocksize = self.blocksize;
+        self.frames_mut()
+            .zip(ramp(from, to, blocksize))
+            .for_each(|(frame, gain)| frame.iter_mut().for_each(|s| *s *= gain));
+    }
+
+    /// Mix in `other`, with the mix changing linearly across the block. See
+    /// [Buffer::crossfade](Buffer<Mono, C>::crossfade)
+    ///
+    /// Panics if the buffers are different sizes
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer<Interleaved, _> = Buffer::new(2, 3);
+    /// let mut other: Buffer<Interleaved, _> = Buffer::new(2, 3);
+    /// other += 1.0;
+    ///
+    /// buffer.crossfade(&other, 0.0, 1.0);
+    /// assert_eq!(&[0.0, 0.0, 0.5, 0.5, 1.0, 1.0], buffer.samples());
+    /// ```
+    pub fn crossfade<C2: Container<Item = f32>>(
+        &mut self,
+        other: &Buffer<Interleaved, C2>,
+        mix_start: f32,
+        mix_end: f32,
+    ) {
+        assert_eq!(self.channels, other.channels);
+        assert_eq!(self.blocksize, other.blocksize);
+        let blocksize = self.blocksize;
+        self.frames_mut()
+            .zip(other.frames())
+            .zip(ramp(mix_start, mix_end, blocksize))
+            .for_each(|((frame, o), mix)| {
+                frame
+                    .iter_mut()
+                    .zip(o)
+                    .for_each(|(s, o)| *s += (o - *s) * mix)
+            });
+    }
+
+    /// Constant-power pan, from -1.0 (hard left) to 1.0 (hard right). Uses [fast_sin] and [fast_cos] when the
+    /// `fastmaths` feature is enabled
+    ///
+    /// The left channel is scaled by `cos(θ)` and the right by `sin(θ)`, where θ goes from 0 to π/2, so the total
+    /// power stays the same wherever the signal is panned. Buffers without exactly 2 channels are left unchanged.
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut centre: Buffer<Interleaved, _> = Buffer::new(2, 1);
+    /// centre += 1.0;
+    /// centre.pan(0.0);
+    /// let (l, r) = centre.frames().next().unwrap().stereo().map(|(l, r)| (*l, *r)).unwrap();
+    /// assert!((l - r).abs() < 1e-3);
+    /// assert!((l * l + r * r - 1.0).abs() < 1e-3);
+    ///
+    /// let mut left: Buffer<Interleaved, _> = Buffer::new(2, 1);
+    /// left += 1.0;
+    /// left.pan(-1.0);
+    /// assert!((left.samples()[0] - 1.0).abs() < 1e-3);
+    /// assert!(left.samples()[1].abs() < 1e-3);
+    ///
+    /// let mut right: Buffer<Interleaved, _> = Buffer::new(2, 1);
+    /// right += 1.0;
+    /// right.pan(1.0);
+    /// assert!(right.samples()[0].abs() < 1e-3);
+    /// assert!((right.samples()[1] - 1.0).abs() < 1e-3);
+    /// ```
+    ///
+    /// [fast_sin]: crate::fastmaths::FastFloat::fast_sin
+    /// [fast_cos]: crate::fastmaths::FastFloat::fast_cos
+    pub fn pan(&mut self, position: f32) {
+        let theta = (position.clamp(-1.0, 1.0) + 1.0) * core::f32::consts::FRAC_PI_4;
+
+        #[cfg(feature = "fastmaths")]
+        let (left_gain, right_gain) = {
+            use crate::fastmaths::FastFloat;
+            (theta.fast_cos(), theta.fast_sin())
+        };
+
+        #[cfg(not(feature = "fastmaths"))]
+        let (left_gain, right_gain) = (theta.cos(), theta.sin());
+
+        for frame in self.frames_mut() {
+            if let Some((left, right)) = frame.stereo_mut() {
+                *left *= left_gain;
+                *right *= right_gain;
+            }
+        }
+    }
+}
+
+macro_rules! impl_op {
+    ($assign_trait:ident, $assign_method:ident) => {
+        impl<F, S, C> $assign_trait<F> for Buffer<S, C>
+        where
+            F: $assign_trait<F> + Copy + Default,
+            S: StoragePattern,
+            C: MutableContainer<Item = F>,
+        {
+            fn $assign_method(&mut self, rhs: F) {
+                for s in self.samples_mut() {
+                    (*s).$assign_method(rhs);
+                }
+            }
+        }
+
+        impl<F, S, C1, C2> $assign_trait<&Buffer<S, C2>> for Buffer<S, C1>
+        where
+            F: $assign_trait<F> + Copy + Default,
+            S: StoragePattern,
+            C1: MutableContainer<Item = F>,
+            C2: Container<Item = F>,
+        {
+            /// Integer samples follow the usual overflow rules (panic in debug builds, wrap in release). See
+            /// [Buffer::saturating_add_assign] for a saturating alternative.
+            fn $assign_method(&mut self, rhs: &Buffer<S, C2>) {
+                for (s, o) in self.samples_mut().iter_mut().zip(rhs.samples()) {
+                    (*s).$assign_method(*o);
+                }
+            }
+        }
+    };
+}
+
+impl_op!(AddAssign, add_assign);
+impl_op!(SubAssign, sub_assign);
+impl_op!(MulAssign, mul_assign);
+impl_op!(DivAssign, div_assign);
+impl_op!(RemAssign, rem_assign);
+
+impl<F, S, C> Buffer<S, C>
+where
+    F: Copy,
+    S: StoragePattern,
+    C: MutableContainer<Item = F>,
+{
+    /// Combine each sample with the matching sample of `other`, in place: `self[n] = f(self[n], other[n])`
+    ///
+    /// Like the arithmetic operators, but with any function. If the buffers are different lengths, only the
+    /// overlapping samples are changed.
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut a: Buffer<Mono, _> = Buffer::new_from(1, 4, vec![0.1, 0.9, -0.5, 0.3]);
+    /// let b: Buffer<Mono, _> = Buffer::new_from(1, 4, vec![0.5, 0.5, 0.0, 0.3]);
+    ///
+    /// a.zip_map(&b, f32::min);
+    /// assert_eq!(&[0.1, 0.5, -0.5, 0.3], a.samples());
+    /// ```
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// // Waveshape the left channel, with the amount of drive from the right
+    /// let mut signal: Buffer<Channels, _> = Buffer::new_from(2, 2, vec![0.5f32, -0.5, 0.0, 0.0]);
+    /// let drive: Buffer<Channels, _> = Buffer::new_from(2, 2, vec![2.0, 4.0, 1.0, 1.0]);
+    ///
+    /// signal.zip_map(&drive, |x, d| (x * d).clamp(-1.0, 1.0));
+    /// assert_eq!(&[1.0, -1.0, 0.0, 0.0], signal.samples());
+    /// ```
+    pub fn zip_map<C2: Container<Item = F>>(
+        &mut self,
+        other: &Buffer<S, C2>,
+        mut f: impl FnMut(F, F) -> F,
+    ) {
+        for (s, o) in self.samples_mut().iter_mut().zip(other.samples()) {
+            *s = f(*s, *o);
+        }
+    }
+
+    /// Replace each sample with `f(sample)`, in place
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer<Interleaved, _> = Buffer::new_from(2, 2, vec![0.1, 0.2, 0.3, 0.4]);
+    /// buffer.map_in_place(|s| s * 2.0);
+    ///
+    /// assert_eq!(&[0.2, 0.4, 0.6, 0.8], buffer.samples());
+    /// assert_eq!(2, buffer.frames().count());
+    /// ```
+    pub fn map_in_place(&mut self, mut f: impl FnMut(F) -> F) {
+        for s in self.samples_mut() {
+            *s = f(*s);
+        }
+    }
+
+    /// Add the samples of `other`, saturating at the limits of the sample type rather than overflowing
+    ///
+    /// The `+=` operator follows the usual integer overflow rules: it panics in debug builds, and wraps around in
+    /// release builds, turning a loud mix into a harsh click. Use this when mixing integer buffers (e.g. raw device
+    /// samples) which might exceed full scale.
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut a: Buffer<Mono, _> = Buffer::new_from(1, 3, vec![i32::MAX - 10, i32::MIN + 10, 100]);
+    /// let b: Buffer<Mono, _> = Buffer::new_from(1, 3, vec![100, -100, 100]);
+    ///
+    /// a.saturating_add_assign(&b);
+    /// assert_eq!(&[i32::MAX, i32::MIN, 200], a.samples());
+    /// ```
+    pub fn saturating_add_assign<C2: Container<Item = F>>(&mut self, other: &Buffer<S, C2>)
+    where
+        F: SaturatingAdd,
+    {
+        self.zip_map(other, |a, b| a.saturating_add(&b));
+    }
+
+    /// Subtract the samples of `other`, saturating at the limits of the sample type rather than overflowing
+    ///
+    /// See [saturating_add_assign](Self::saturating_add_assign)
+    pub fn saturating_sub_assign<C2: Container<Item = F>>(&mut self, other: &Buffer<S, C2>)
+    where
+        F: SaturatingSub,
+    {
+        self.zip_map(other, |a, b| a.saturating_sub(&b));
+    }
+
+    /// Add the samples of `other`, explicitly wrapping around on overflow (in debug builds too)
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut a: Buffer<Mono, _> = Buffer::new_from(1, 2, vec![i32::MAX, 1]);
+    /// let b: Buffer<Mono, _> = Buffer::new_from(1, 2, vec![1, 1]);
+    ///
+    /// a.wrapping_add_assign(&b);
+    /// assert_eq!(&[i32::MIN, 2], a.samples());
+    /// ```
+    pub fn wrapping_add_assign<C2: Container<Item = F>>(&mut self, other: &Buffer<S, C2>)
+    where
+        F: WrappingAdd,
+    {
+        self.zip_map(other, |a, b| a.wrapping_add(&b));
+    }
+
+    /// Subtract the samples of `other`, explicitly wrapping around on overflow (in debug builds too)
+    pub fn wrapping_sub_assign<C2: Container<Item = F>>(&mut self, other: &Buffer<S, C2>)
+    where
+        F: WrappingSub,
+    {
+        self.zip_map(other, |a, b| a.wrapping_sub(&b));
+    }
+}
+
+impl<F, S, C> Buffer<S, C>
+where
+    F: Copy,
+    S: StoragePattern,
+    C: Container<Item = F>,
+{
+    /// Write `f(sample)` for each sample into `dest`, which may hold a different sample type
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let source: Buffer<Channels, _> = Buffer::new_from(2, 2, vec![1.0f32, 2.0, 3.0, 4.0]);
+    /// let mut doubled: Buffer<Channels, Box<[i16]>> = Buffer::new(2, 2);
+    ///
+    /// source.map_into(&mut doubled, |s| (s * 2.0) as i16);
+    /// assert_eq!(&[2, 4, 6, 8], doubled.samples());
+    /// ```
+    ///
+    /// Panics if the buffers have different dimensions
+    pub fn map_into<G, C2: MutableContainer<Item = G>>(
+        &self,
+        dest: &mut Buffer<S, C2>,
+        mut f: impl FnMut(F) -> G,
+    ) {
+        assert_eq!(self.channels, dest.channels);
+        assert_eq!(self.blocksize, dest.blocksize);
+        for (d, s) in dest.samples_mut().iter_mut().zip(self.samples()) {
+            *d = f(*s);
+        }
+    }
+}
+
+impl<F, S, C> MulAddAssign<F, F> for Buffer<S, C>
+where
+    F: Copy + Default + MulAddAssign<F>,
+    S: StoragePattern,
+    C: MutableContainer<Item = F>,
+{
+    fn mul_add_assign(&mut self, a: F, b: F) {
+        for s in self.samples_mut() {
+            s.mul_add_assign(a, b);
+        }
+    }
+}
+
+impl<F, S, C1, C2> MulAddAssign<&Buffer<S, C2>, F> for Buffer<S, C1>
+where
+    Self: for<'a> MulAssign<&'a Buffer<S, C2>> + AddAssign<F>,
+    F: Copy + Default,
+    S: StoragePattern,
+    C1: MutableContainer<Item = F>,
+    C2: Container<Item = F>,
+{
+    fn mul_add_assign(&mut self, a: &Buffer<S, C2>, b: F) {
+        self.mul_assign(a);
+        self.add_assign(b);
+    }
+}
+
+impl<F, S, C1, C2> MulAddAssign<F, &Buffer<S, C2>> for Buffer<S, C1>
+where
+    Self: MulAssign<F> + for<'a> AddAssign<&'a Buffer<S, C2>>,
+    F: Copy + Default,
+    S: StoragePattern,
+    C1: MutableContainer<Item = F>,
+    C2: Container<Item = F>,
+{
+    fn mul_add_assign(&mut self, a: F, b: &Buffer<S, C2>) {
+        self.mul_assign(a);
+        self.add_assign(b);
+    }
+}
+
+impl<F, S, C1, C2, C3> MulAddAssign<&Buffer<S, C2>, &Buffer<S, C3>> for Buffer<S, C1>
+where
+    Self: for<'a> MulAssign<&'a Buffer<S, C2>> + for<'a> AddAssign<&'a Buffer<S, C3>>,
+    F: Copy + Default + MulAddAssign<F, F>,
+    S: StoragePattern,
+    C1: MutableContainer<Item = F>,
+    C2: Container<Item = F>,
+    C3: Container<Item = F>,
+{
+    #[inline(never)]
+    fn mul_add_assign(&mut self, a: &Buffer<S, C2>, b: &Buffer<S, C3>) {
+        // Seems to be faster than doing it in a single loop - on m4 and m7
+        self.mul_assign(a);
+        self.add_assign(b);
+    }
+}
+
+impl<F, S, C> Neg for Buffer<S, C>
+where
+    F: Neg<Output = F> + Copy + Default,
+    S: StoragePattern,
+    C: MutableContainer<Item = F>,
+{
+    type Output = Self;
+
+    fn neg(mut self) -> Self::Output {
+        for s in self.samples_mut() {
+            *s = s.neg();
+        }
+
+        self
+    }
+}
+
+/// Block operations for f32 buffers. With the `cmsis` feature enabled these use the vectorised CMSIS-DSP
+/// functions when building for the device, otherwise they are the same as the equivalent operators.
+///
+/// The CMSIS kernels are only built for the device, so the doc tests, which run on the host, only exercise the
+/// operator fallback.
+impl<S, C> Buffer<S, C>
+where
+    S: StoragePattern,
+    C: MutableContainer<Item = f32>,
+{
+    /// Multiply every sample by `gain`. Equivalent to `*= gain`
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let data: Vec<f32> = (0..67).map(|n| n as f32 * 0.1 - 3.0).collect();
+    /// let mut buffer: Buffer<Mono, _> = Buffer::new_from(1, 67, data.clone());
+    ///
+    /// buffer.scale(0.7);
+    /// for (s, d) in buffer.samples().iter().zip(&data) {
+    ///     assert!((s - d * 0.7).abs() < 1e-6);
+    /// }
+    /// ```
+    pub fn scale(&mut self, gain: f32) {
+        #[cfg(all(feature = "cmsis", target_arch = "arm"))]
+        {
+            let samples = self.samples_mut();
+            let dst = samples.as_mut_ptr();
+            unsafe { crate::ffi::cmsis::arm_scale_f32(dst, gain, dst, samples.len() as u32) };
+        }
+
+        #[cfg(not(all(feature = "cmsis", target_arch = "arm")))]
+        self.mul_assign(gain);
+    }
+
+    /// Add the samples of `other`. Equivalent to `+= other`
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let a: Vec<f32> = (0..67).map(|n| n as f32 * 0.1 - 3.0).collect();
+    /// let b: Vec<f32> = (0..67).map(|n| (n as f32 * 0.3).sin()).collect();
+    /// let mut buffer: Buffer<Mono, _> = Buffer::new_from(1, 67, a.clone());
+    ///
+    /// buffer.add_buffer(&Buffer::mono_ref(&b));
+    /// for ((s, a), b) in buffer.samples().iter().zip(&a).zip(&b) {
+    ///     assert!((s - (a + b)).abs() < 1e-6);
+    /// }
+    /// ```
+    pub fn add_buffer<C2: Container<Item = f32>>(&mut self, other: &Buffer<S, C2>) {
+        #[cfg(all(feature = "cmsis", target_arch = "arm"))]
+        {
+            let samples = self.samples_mut();
+            let len = samples.len().min(other.samples().len());
+            let dst = samples.as_mut_ptr();
+            unsafe {
+                crate::ffi::cmsis::arm_add_f32(dst, other.samples().as_ptr(), dst, len as u32)
+            };
+        }
+
+        #[cfg(not(all(feature = "cmsis", target_arch = "arm")))]
+        self.add_assign(other);
+    }
+
+    /// Multiply by the samples of `other`. Equivalent to `*= other`
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let a: Vec<f32> = (0..67).map(|n| n as f32 * 0.1 - 3.0).collect();
+    /// let b: Vec<f32> = (0..67).map(|n| (n as f32 * 0.3).sin()).collect();
+    /// let mut buffer: Buffer<Mono, _> = Buffer::new_from(1, 67, a.clone());
+    ///
+    /// buffer.multiply_buffer(&Buffer::mono_ref(&b));
+    /// for ((s, a), b) in buffer.samples().iter().zip(&a).zip(&b) {
+    ///     assert!((s - a * b).abs() < 1e-6);
+    /// }
+    /// ```
+    pub fn multiply_buffer<C2: Container<Item = f32>>(&mut self, other: &Buffer<S, C2>) {
+        #[cfg(all(feature = "cmsis", target_arch = "arm"))]
+        {
+            let samples = self.samples_mut();
+            let len = samples.len().min(other.samples().len());
+            let dst = samples.as_mut_ptr();
+            unsafe {
+                crate::ffi::cmsis::arm_mult_f32(dst, other.samples().as_ptr(), dst, len as u32)
+            };
+        }
+
+        #[cfg(not(all(feature = "cmsis", target_arch = "arm")))]
+        self.mul_assign(other);
+    }
+
+    /// Multiply by `gain`, then add the samples of `other`. Equivalent to `mul_add_assign(gain, other)`
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let a: Vec<f32> = (0..67).map(|n| n as f32 * 0.1 - 3.0).collect();
+    /// let b: Vec<f32> = (0..67).map(|n| (n as f32 * 0.3).sin()).collect();
+    /// let mut buffer: Buffer<Mono, _> = Buffer::new_from(1, 67, a.clone());
+    ///
+    /// buffer.mul_add_buffer(0.5, &Buffer::mono_ref(&b));
+    /// for ((s, a), b) in buffer.samples().iter().zip(&a).zip(&b) {
+    ///     assert!((s - (a * 0.5 + b)).abs() < 1e-6);
+    /// }
+    /// ```
+    pub fn mul_add_buffer<C2: Container<Item = f32>>(&mut self, gain: f32, other: &Buffer<S, C2>) {
+        self.scale(gain);
+        self.add_buffer(other);
+    }
+
+    /// Replace the contents with the sum of several sources, each with its own gain, in a single pass
+    ///
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let voice1: Buffer<Mono, _> = Buffer::new_from(1, 3, vec![1.0, 0.5, -1.0]);
+    /// let voice2: Buffer<Mono, _> = Buffer::new_from(1, 3, vec![0.5, 0.5, 1.0]);
+    /// let mut mix: Buffer<Mono, Box<[f32]>> = Buffer::new_mono(3);
+    ///
+    /// mix.mix_from(&[(0.5, &voice1), (0.25, &voice2)]);
+    /// assert_eq!(&[0.625, 0.375, -0.25], mix.samples());
+    ///
+    /// // no sources gives silence
+    /// mix.mix_from::<Box<[f32]>>(&[]);
+    /// assert_eq!(&[0.0; 3], mix.samples());
+    /// ```
+    ///
+    /// Panics if any source is a different length
+    pub fn mix_from<C2: Container<Item = f32>>(&mut self, sources: &[(f32, &Buffer<S, C2>)]) {
+        let samples = self.samples_mut();
+        for (_, source) in sources {
+            assert_eq!(samples.len(), source.samples().len());
+        }
+
+        for (n, s) in samples.iter_mut().enumerate() {
+            *s = sources
+                .iter()
+                .map(|(gain, source)| gain * source.samples()[n])
+                .sum();
+        }
+    }
+
+    /// Limit every sample to the range `min..=max`, in place
+    ///
+    /// Converting a sample outside of -1.0 to 1.0 to an integer format wraps around, giving a loud click. Clamping
+    /// the output buffer before conversion prevents this. NaN samples are left as they are.
+    ///
+    /// Panics if `min > max`
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer<Mono, _> = Buffer::new_from(1, 4, vec![-2.0, -0.5, 0.5, 2.0]);
+    /// buffer.clamp(-1.0, 1.0);
+    /// assert_eq!(&[-1.0, -0.5, 0.5, 1.0], buffer.samples());
+    /// ```
+    pub fn clamp(&mut self, min: f32, max: f32) {
+        self.samples_mut()
+            .iter_mut()
+            .for_each(|s| *s = s.clamp(min, max));
+    }
+
+    /// Limit every sample to the range `-limit..=limit`, in place. See [clamp](Self::clamp)
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer<Interleaved, _> = Buffer::new_from(2, 2, vec![-1.5, 0.25, -0.75, 3.0]);
+    /// buffer.clamp_symmetric(1.0);
+    /// assert_eq!(&[-1.0, 0.25, -0.75, 1.0], buffer.samples());
+    /// ```
+    pub fn clamp_symmetric(&mut self, limit: f32) {
+        self.clamp(-limit, limit);
+    }
+
+    /// Soft clip every sample with [fast_tanh], in place
+    ///
+    /// Unlike [clamp](Self::clamp), this also changes samples within range, smoothly squashing them towards
+    /// ±1.0 instead of cutting off abruptly.
+    /// ```
+    /// # use owl_patch::sample_buffer::*;
+    /// let mut buffer: Buffer<Mono, _> = Buffer::new_from(1, 4, vec![-8.0, -0.1, 0.0, 8.0]);
+    /// buffer.soft_clip();
+    /// assert!(buffer.samples().iter().all(|s| (-1.0..=1.0).contains(s)));
+    /// assert_eq!(0.0, buffer[2]);
+    /// assert!((buffer[1] + 0.1).abs() < 0.001);
+    /// ```
+    ///
+    /// [fast_tanh]: crate::fastmaths::FastFloat::fast_tanh
+    #[cfg(feature = "fastmaths")]
+    pub fn soft_clip(&mut self) {
+        use crate::fastmaths::FastFloat;
+
+        self.samples_mut()
+            .iter_mut()
+            .for_each(|s| *s = s.fast_tanh());
+    }
+}
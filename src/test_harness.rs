@@ -1,15 +1,52 @@
 extern crate std;
 
 use crate::ffi::program_vector as ffi;
-use crate::program_vector::ProgramVector;
+use crate::ffi::service_call as service_ffi;
+use crate::midi_message::MidiMessage;
 use crate::program_vector::ProgramVectorChecksum;
+use crate::program_vector::{AudioBuffers, AudioFormat, ProgramVector};
+use crate::sample_buffer::{Buffer, ConvertFromClamped, ConvertInto, Interleaved};
+use crate::{PatchButtonId, PatchParameterId};
 
-static mut AUDIO_IN: [i32; 64] = [0; 64];
-static mut AUDIO_OUT: [i32; 64] = [0; 64];
+// Enough for 8 channels at the maximum block size of 32
+static mut AUDIO_IN: [i32; 256] = [0; 256];
+static mut AUDIO_OUT: [i32; 256] = [0; 256];
 
 static mut PARAMETERS: [i16; 8] = [0; 8];
+static mut OUTPUT_PARAMETERS: [i16; 8] = [0; 8];
+static OUTPUT_BUTTONS: core::sync::atomic::AtomicU16 = core::sync::atomic::AtomicU16::new(0);
+static PARAMETER_NAMES: std::sync::Mutex<[Option<std::string::String>; 8]> =
+    std::sync::Mutex::new([const { None }; 8]);
+
+static mut BLOCKS_REMAINING: usize = 0;
+static MIDI_RECEIVE: core::sync::atomic::AtomicPtr<()> =
+    core::sync::atomic::AtomicPtr::new(core::ptr::null_mut());
+static SENT_MIDI: std::sync::Mutex<std::vec::Vec<[u8; 4]>> =
+    std::sync::Mutex::new(std::vec::Vec::new());
+static REGISTERED_PATCH: std::sync::Mutex<Option<(std::string::String, u8, u8)>> =
+    std::sync::Mutex::new(None);
+static HARDWARE_VERSION: core::sync::atomic::AtomicU8 = core::sync::atomic::AtomicU8::new(0);
+static CALIBRATION: std::sync::Mutex<Option<[f32; 4]>> = std::sync::Mutex::new(None);
+static EXPECTED_ERROR: std::sync::Mutex<Option<std::string::String>> = std::sync::Mutex::new(None);
+static RESOURCES: std::sync::Mutex<std::vec::Vec<(std::ffi::CString, std::vec::Vec<u8>)>> =
+    std::sync::Mutex::new(std::vec::Vec::new());
 
 pub unsafe fn program_vector() -> ProgramVector {
+    program_vector_with_channels(0)
+}
+
+/// As [program_vector], but with the channel count reported by the OS set to `channels`. 0 means
+/// the device default (2), the maximum is 8
+///
+/// ```
+/// # use owl_patch::test_harness;
+/// # use owl_patch::program_vector::ChannelLayout;
+/// let mut pv = unsafe { test_harness::program_vector_with_channels(4) };
+/// assert_eq!(ChannelLayout::Multi(4), pv.audio().channel_layout());
+/// ```
+pub unsafe fn program_vector_with_channels(channels: u8) -> ProgramVector {
+    assert!(channels <= 8);
+
     #[cfg(feature = "fastmaths")]
     crate::fastmaths::set_default_tables();
 
@@ -17,32 +54,412 @@ pub unsafe fn program_vector() -> ProgramVector {
     let pv = crate::program_vector::PROGRAM_VECTOR.assume_init_mut();
 
     pv.checksum = ProgramVectorChecksum::V13 as u8;
-    pv.hardware_version = 0;
+    pv.hardware_version = HARDWARE_VERSION.load(core::sync::atomic::Ordering::Relaxed);
     pv.audio_input = core::ptr::addr_of!(AUDIO_IN) as *mut i32;
     pv.audio_output = core::ptr::addr_of!(AUDIO_OUT) as *mut i32;
-    pv.audio_format = ffi::AUDIO_FORMAT_24B16 as u8;
+    pv.audio_format = ffi::AUDIO_FORMAT_24B16 as u8 | channels;
     pv.audio_blocksize = 32;
     pv.audio_samplingrate = 44100;
     pv.parameters_size = 8;
     pv.parameters = core::ptr::addr_of!(PARAMETERS) as *mut i16;
     pv.buttons = 0;
     pv.error = 0;
-    pv.registerPatch = None;
-    pv.registerPatchParameter = None;
+    pv.registerPatch = Some(register_patch);
+    pv.registerPatchParameter = Some(register_patch_parameter);
     pv.programReady = Some(program_ready);
-    pv.programStatus = None;
-    pv.serviceCall = None;
+    pv.programStatus = Some(program_status);
+    pv.serviceCall = Some(service_call);
     pv.cycles_per_block = 0;
     pv.heap_bytes_used = 0;
     pv.message = core::ptr::null_mut();
-    pv.setButton = None;
-    pv.setPatchParameter = None;
+    pv.setButton = Some(set_button);
+    pv.setPatchParameter = Some(set_patch_parameter);
     pv.buttonChangedCallback = None;
     pv.heapLocations = core::ptr::null_mut();
 
     ProgramVector::new(pv, c"test".as_ptr())
 }
 
+/// Set the raw value of an input parameter (4096 == 1.0)
+pub fn set_parameter(pid: PatchParameterId, value: i16) {
+    unsafe { PARAMETERS[pid as usize] = value };
+}
+
+/// Set the raw audio input samples, as the OS would supply them (interleaved, 24B16 format)
+pub fn set_audio_input(samples: &[i32]) {
+    #[allow(static_mut_refs)]
+    unsafe {
+        AUDIO_IN[..samples.len()].copy_from_slice(samples)
+    };
+}
+
+/// Get the raw audio output samples, as the OS would receive them (interleaved, 24B16 format)
+pub fn audio_output() -> std::vec::Vec<i32> {
+    #[allow(static_mut_refs)]
+    let pv = unsafe { crate::program_vector::PROGRAM_VECTOR.assume_init_ref() };
+    let channels = match pv.audio_format & ffi::AUDIO_FORMAT_CHANNEL_MASK as u8 {
+        0 => 2,
+        n => n as usize,
+    };
+    let len = pv.audio_blocksize as usize * channels;
+    #[allow(static_mut_refs)]
+    unsafe {
+        AUDIO_OUT[..len].to_vec()
+    }
+}
+
+/// Get the current debug message
+pub fn debug_message() -> Option<std::string::String> {
+    #[allow(static_mut_refs)]
+    let pv = unsafe { crate::program_vector::PROGRAM_VECTOR.assume_init_ref() };
+    (!pv.message.is_null()).then(|| {
+        unsafe { core::ffi::CStr::from_ptr(pv.message) }
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+/// Get the name a parameter was registered with
+pub fn parameter_name(pid: PatchParameterId) -> Option<std::string::String> {
+    PARAMETER_NAMES.lock().unwrap()[pid as usize].clone()
+}
+
+/// Get the name and input / output channel counts the patch was most recently registered with
+pub fn registered_patch() -> Option<(std::string::String, u8, u8)> {
+    REGISTERED_PATCH.lock().unwrap().clone()
+}
+
+unsafe extern "C" fn register_patch(name: *const core::ffi::c_char, inputs: u8, outputs: u8) {
+    let name = core::ffi::CStr::from_ptr(name)
+        .to_string_lossy()
+        .into_owned();
+    *REGISTERED_PATCH.lock().unwrap() = Some((name, inputs, outputs));
+}
+
+unsafe extern "C" fn register_patch_parameter(id: u8, name: *const core::ffi::c_char) {
+    let name = core::ffi::CStr::from_ptr(name)
+        .to_string_lossy()
+        .into_owned();
+    PARAMETER_NAMES.lock().unwrap()[id as usize] = Some(name);
+}
+
+/// Get the raw value most recently set on an output parameter
+pub fn output_parameter(pid: PatchParameterId) -> i16 {
+    unsafe { OUTPUT_PARAMETERS[pid as usize] }
+}
+
+unsafe extern "C" fn set_patch_parameter(id: u8, value: i16) {
+    OUTPUT_PARAMETERS[id as usize] = value;
+}
+
+/// Simulate a button change event from the OS
+pub fn change_button(bid: PatchButtonId, state: u16, samples: u16) {
+    #[allow(static_mut_refs)]
+    let pv = unsafe { crate::program_vector::PROGRAM_VECTOR.assume_init_ref() };
+    if let Some(callback) = pv.buttonChangedCallback {
+        unsafe { callback(bid as u8, state, samples) };
+    }
+}
+
+/// Get the state most recently set on an output button
+pub fn output_button(bid: PatchButtonId) -> bool {
+    OUTPUT_BUTTONS.load(core::sync::atomic::Ordering::Relaxed) & (1 << bid as u8) != 0
+}
+
+unsafe extern "C" fn set_button(id: u8, state: u16, _samples: u16) {
+    if state != 0 {
+        OUTPUT_BUTTONS.fetch_or(1 << id, core::sync::atomic::Ordering::Relaxed);
+    } else {
+        OUTPUT_BUTTONS.fetch_and(!(1 << id), core::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// Change the sample rate and block size, as the OS would. Block size must be no more than 32
+pub fn set_audio_settings(sample_rate: u32, blocksize: u16) {
+    assert!(blocksize <= 32);
+    // AudioBuffers holds shared references to these fields, so write through the same atomics rather than
+    // taking a mutable reference to the whole program vector
+    let pv =
+        core::ptr::addr_of_mut!(crate::program_vector::PROGRAM_VECTOR) as *mut ffi::ProgramVector;
+    unsafe {
+        core::sync::atomic::AtomicU32::from_ptr(core::ptr::addr_of_mut!((*pv).audio_samplingrate))
+            .store(sample_rate, core::sync::atomic::Ordering::Relaxed);
+        core::sync::atomic::AtomicU16::from_ptr(core::ptr::addr_of_mut!((*pv).audio_blocksize))
+            .store(blocksize, core::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// Set the number of cycles the OS reports were used for the previous block
+pub fn set_cycles_per_block(cycles: u32) {
+    #[allow(static_mut_refs)]
+    let pv = unsafe { crate::program_vector::PROGRAM_VECTOR.assume_init_mut() };
+    pv.cycles_per_block = cycles;
+}
+
+/// Add a resource, which the OS will provide when it's loaded by name
+///
+/// ```
+/// # use owl_patch::test_harness;
+/// let amen: Vec<u8> = (0..3000).map(|n| (n * 7) as u8).collect();
+/// test_harness::add_resource("AmenBreak.raw", &amen);
+/// let mut pv = unsafe { test_harness::program_vector() };
+///
+/// let mut loaded = Vec::new();
+/// let mut buffer = [0; 1024];
+/// let size = pv.load_resource_streamed(c"AmenBreak.raw", &mut buffer, |chunk| {
+///     loaded.extend_from_slice(chunk)
+/// });
+///
+/// assert_eq!(Ok(3000), size);
+/// assert_eq!(amen, loaded);
+/// ```
+pub fn add_resource(name: &str, data: &[u8]) {
+    let name = std::ffi::CString::new(name).unwrap();
+    let mut resources = RESOURCES.lock().unwrap();
+    resources.retain(|(n, _)| *n != name);
+    resources.push((name, data.to_vec()));
+}
+
+/// Set the hardware version reported by the OS. Only affects program vectors created afterwards
+///
+/// ```
+/// # use owl_patch::{program_vector::OWL_MODULAR_HARDWARE, test_harness};
+/// # use owl_patch::volts_per_octave::Volts;
+/// test_harness::set_hardware_version(OWL_MODULAR_HARDWARE);
+/// let mut pv = unsafe { test_harness::program_vector() };
+///
+/// // Without calibration data, the default for the owl modular is used
+/// let (vps_in, _) = pv.volts_per_sample();
+/// assert_eq!(Volts((1.0 + 0.06382) * -4.29), vps_in.sample_to_volts(1.0));
+/// ```
+pub fn set_hardware_version(version: u8) {
+    HARDWARE_VERSION.store(version, core::sync::atomic::Ordering::Relaxed);
+}
+
+/// Set the volts per octave calibration data returned by the OS. Without it, the device defaults are used
+///
+/// Must be set before [ProgramVector::volts_per_sample] is first called, as the result is cached.
+/// ```
+/// # use owl_patch::test_harness;
+/// # use owl_patch::volts_per_octave::Volts;
+/// let mut pv = unsafe { test_harness::program_vector() };
+/// test_harness::set_calibration(4.0, 0.0, -5.0, 0.25);
+///
+/// let (vps_in, vps_out) = pv.volts_per_sample();
+/// assert_eq!(Volts(4.0), vps_in.sample_to_volts(1.0));
+/// // The OS passes the values as fixed point, so they may not be exact
+/// assert!((vps_out.sample_to_volts(1.0).0 + 3.75).abs() < 1e-3);
+/// ```
+pub fn set_calibration(
+    input_scalar: f32,
+    input_offset: f32,
+    output_scalar: f32,
+    output_offset: f32,
+) {
+    *CALIBRATION.lock().unwrap() = Some([input_offset, input_scalar, output_offset, output_scalar]);
+}
+
+/// Number of audio blocks to process before exiting (default 0)
+pub fn run_blocks(blocks: usize) {
+    unsafe { BLOCKS_REMAINING = blocks };
+}
+
 unsafe extern "C" fn program_ready() {
-    std::process::exit(0);
+    if BLOCKS_REMAINING == 0 {
+        std::process::exit(0);
+    }
+    BLOCKS_REMAINING -= 1;
+}
+
+/// Expect the patch to raise an error with this message. The process exits successfully once the
+/// OS is told about the error, as long as the message and error status were set beforehand
+pub fn expect_error(message: &str) {
+    *EXPECTED_ERROR.lock().unwrap() = Some(message.into());
+}
+
+unsafe extern "C" fn program_status(status: ffi::ProgramVectorAudioStatus) {
+    #[allow(static_mut_refs)]
+    let pv = unsafe { crate::program_vector::PROGRAM_VECTOR.assume_init_ref() };
+    let expected = EXPECTED_ERROR.lock().unwrap().take();
+
+    let ok = status as i32 == ffi::ProgramVectorAudioStatus::AUDIO_ERROR_STATUS as i32
+        && pv.error == ffi::CONFIGURATION_ERROR_STATUS as i8
+        && expected.is_some()
+        && debug_message() == expected;
+
+    std::process::exit(if ok { 0 } else { 1 });
+}
+
+/// Deliver a midi message to the patch, as if received by the OS. [ProgramVector::midi] must have been called first
+pub fn inject_midi(message: MidiMessage) {
+    let receive = MIDI_RECEIVE.load(core::sync::atomic::Ordering::Relaxed);
+    assert!(!receive.is_null(), "midi not initialised");
+    let receive: extern "C" fn(u8, u8, u8, u8) = unsafe { core::mem::transmute(receive) };
+    let [port, d0, d1, d2] = message.as_bytes();
+    receive(port, d0, d1, d2);
+}
+
+/// Take all midi messages sent by the patch so far
+pub fn sent_midi() -> std::vec::Vec<MidiMessage> {
+    core::mem::take(&mut *SENT_MIDI.lock().unwrap())
+        .into_iter()
+        .map(|[port, d0, d1, d2]| MidiMessage::new(port, d0, d1, d2))
+        .collect()
+}
+
+extern "C" fn send_midi(port: u8, d0: u8, d1: u8, d2: u8) {
+    SENT_MIDI.lock().unwrap().push([port, d0, d1, d2]);
+}
+
+/// Only the midi callbacks, device parameters and resources are supported, any other service returns an error
+unsafe extern "C" fn service_call(
+    service: core::ffi::c_int,
+    params: *mut *mut core::ffi::c_void,
+    len: core::ffi::c_int,
+) -> core::ffi::c_int {
+    const ERROR: core::ffi::c_int = -1;
+
+    let params = core::slice::from_raw_parts(params, len.max(0) as usize);
+
+    match service as u32 {
+        service_ffi::OWL_SERVICE_GET_PARAMETERS => {
+            let Some(values) = *CALIBRATION.lock().unwrap() else {
+                return ERROR;
+            };
+            // Pairs of (name, value), in the order IO, IS, OO, OS
+            for (pair, value) in params.as_chunks::<2>().0.iter().zip(values) {
+                *(pair[1] as *mut i32) = (value * u16::MAX as f32) as i32;
+            }
+        }
+        service_ffi::OWL_SERVICE_REGISTER_CALLBACK | service_ffi::OWL_SERVICE_REQUEST_CALLBACK
+            if params.len() >= 2
+                && core::ffi::CStr::from_ptr(params[0] as *const _)
+                    == service_ffi::SYSTEM_FUNCTION_MIDI =>
+        {
+            if service as u32 == service_ffi::OWL_SERVICE_REGISTER_CALLBACK {
+                MIDI_RECEIVE.store(params[1] as *mut (), core::sync::atomic::Ordering::Relaxed);
+            } else {
+                *(params[1] as *mut *mut ()) = send_midi as *mut ();
+            }
+        }
+        service_ffi::OWL_SERVICE_LOAD_RESOURCE if params.len() >= 4 => {
+            let name = core::ffi::CStr::from_ptr(params[0] as *const _);
+            let resources = RESOURCES.lock().unwrap();
+            let Some((_, data)) = resources.iter().find(|(n, _)| n.as_c_str() == name) else {
+                return ERROR;
+            };
+            let buffer = *(params[1] as *mut *mut u8);
+            let offset = *(params[2] as *const usize);
+            let size = params[3] as *mut usize;
+
+            if buffer.is_null() {
+                // Resources aren't memory mapped, just report the size
+                *size = data.len();
+            } else {
+                let Some(remaining) = data.get(offset..) else {
+                    return ERROR;
+                };
+                let len = (*size).min(remaining.len());
+                core::ptr::copy_nonoverlapping(remaining.as_ptr(), buffer, len);
+                *size = len;
+            }
+        }
+        _ => return ERROR,
+    }
+    service_ffi::OWL_SERVICE_OK as core::ffi::c_int
+}
+
+/// Feed `input` through an audio closure, a block at a time, and collect the output
+///
+/// The closure is the same as would be passed to [AudioBuffers::run_f32], but is only run for as many blocks as
+/// there are in `input` (interleaved samples, with a whole number of blocks) rather than forever. Samples pass
+/// through the raw format given by `audio.settings.format`, the same as on a device, so allow for small errors.
+///
+/// ```
+/// # use owl_patch::test_harness;
+/// let mut pv = unsafe { test_harness::program_vector() };
+/// let input: Vec<f32> = (0..128).map(|n| (n as f32 * 0.1).sin()).collect();
+///
+/// let output = test_harness::process_blocks(pv.audio(), &input, |input, output| {
+///     for (o, i) in output.samples_mut().iter_mut().zip(input.samples()) {
+///         *o = i * 0.5;
+///     }
+/// });
+///
+/// assert_eq!(128, output.len());
+/// for (o, i) in output.iter().zip(&input) {
+///     assert!((o - i * 0.5).abs() < 1e-4);
+/// }
+/// ```
+pub fn process_blocks(
+    audio: &mut AudioBuffers,
+    input: &[f32],
+    f: impl FnMut(&Buffer<Interleaved, Box<[f32]>>, &mut Buffer<Interleaved, Box<[f32]>>),
+) -> std::vec::Vec<f32> {
+    let block_len = audio.settings.blocksize * audio.settings.channels;
+    assert_eq!(
+        0,
+        input.len() % block_len,
+        "input must be a whole number of blocks"
+    );
+
+    // Bits of a full scale i32 sample that don't fit in the raw format, see AudioBuffers
+    let shift = match audio.settings.format {
+        AudioFormat::Format24B16 => 16,
+        AudioFormat::Format24B32 => 8,
+    };
+
+    let mut f = crate::program_vector::f32_adapter(&audio.settings, f);
+    let mut output = std::vec::Vec::with_capacity(input.len());
+
+    for block in input.chunks_exact(block_len) {
+        #[allow(static_mut_refs)]
+        let raw_in = unsafe { &mut AUDIO_IN[..block_len] };
+        for (raw, sample) in raw_in.iter_mut().zip(block) {
+            let mut full_scale = 0i32;
+            full_scale.convert_from_clamped(*sample);
+            *raw = full_scale >> shift;
+        }
+
+        audio.process_block(&mut f);
+
+        #[allow(static_mut_refs)]
+        let raw_out = unsafe { &AUDIO_OUT[..block_len] };
+        output.extend(
+            raw_out
+                .iter()
+                .map(|raw| -> f32 { (raw << shift).convert_into() }),
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip_error(audio: &mut AudioBuffers) -> f32 {
+        let input: std::vec::Vec<f32> = (0..128).map(|n| (n as f32 * 0.1).sin() * 0.9).collect();
+        let output = process_blocks(audio, &input, |input, output| {
+            output.samples_mut().copy_from_slice(input.samples());
+        });
+        output
+            .iter()
+            .zip(&input)
+            .map(|(o, i)| (o - i).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_process_blocks_uses_audio_format() {
+        let mut pv = unsafe { program_vector() };
+        let audio = pv.audio();
+
+        let error = round_trip_error(audio);
+        assert!(error < 1e-4 && error > 1e-6, "24B16 error {error}");
+
+        audio.settings.format = AudioFormat::Format24B32;
+        let error = round_trip_error(audio);
+        assert!(error < 1e-6, "24B32 error {error}");
+    }
 }
@@ -47,6 +47,52 @@ impl VoltsPerSample {
         Self { scalar, offset }
     }
 
+    /// Check that the calibration data looks sensible: the scalar should be non-zero, and both values
+    /// finite and within a reasonable magnitude.
+    ///
+    /// Use this to detect garbage calibration data from the device
+    /// ```
+    /// # use owl_patch::volts_per_octave::*;
+    /// // default calibration
+    /// assert!(VoltsPerSample::new(2.0, 0.0).is_plausible());
+    /// // Owl modular calibration
+    /// assert!(VoltsPerSample::new(-4.29, -0.06382).is_plausible());
+    /// assert!(VoltsPerSample::new(-4.642, 0.1208).is_plausible());
+    ///
+    /// assert!(!VoltsPerSample::new(0.0, 0.0).is_plausible());
+    /// assert!(!VoltsPerSample::new(f32::NAN, 0.0).is_plausible());
+    /// assert!(!VoltsPerSample::new(2.0, f32::INFINITY).is_plausible());
+    /// assert!(!VoltsPerSample::new(5000.0, 0.0).is_plausible());
+    /// assert!(!VoltsPerSample::new(2.0, 3.0).is_plausible());
+    /// ```
+    pub fn is_plausible(self) -> bool {
+        const MIN_SCALAR: f32 = 0.1;
+        const MAX_SCALAR: f32 = 100.0;
+        const MAX_OFFSET: f32 = 1.0;
+
+        let scalar = self.scalar.abs();
+        (MIN_SCALAR..=MAX_SCALAR).contains(&scalar) && self.offset.abs() <= MAX_OFFSET
+    }
+
+    /// Adjust the offset, keeping the same scalar, so that `known_sample` converts to `known_volts`
+    ///
+    /// ```
+    /// # use owl_patch::volts_per_octave::*;
+    /// let vps = VoltsPerSample::new(-4.29, -0.06382);
+    /// // 0.1 is measured at an input receiving 0V
+    /// let vps = vps.calibrate(0.1, Volts(0.0));
+    ///
+    /// assert_eq!(Volts(0.0), vps.sample_to_volts(0.1));
+    /// assert_eq!(0.1, vps.volts_to_sample(0.0));
+    /// assert!(vps.is_plausible());
+    /// ```
+    pub fn calibrate(self, known_sample: f32, known_volts: impl Into<Volts>) -> Self {
+        Self {
+            scalar: self.scalar,
+            offset: known_sample - known_volts.into().0 / self.scalar,
+        }
+    }
+
     /// Convert a sample value to a frequency
     pub fn sample_to_freq(self, sample: f32) -> Frequency {
         (self * sample).into()
@@ -221,3 +267,255 @@ impl From<Note> for Frequency {
         volts.into()
     }
 }
+
+/// Tuning system used to convert between notes and frequencies
+///
+/// The free conversions between [Note] and [Frequency] always use 12 tone equal temperament with A4 (note 69)
+/// at 440Hz, which is also the [Default] tuning. Use this for other reference pitches, or other equal divisions
+/// of the octave.
+///
+/// ```
+/// # use owl_patch::volts_per_octave::*;
+/// let tuning = Tuning::new(432.0, Note(69), 12.0);
+/// # let close = |a: Frequency, b: f32| (a.0 / b - 1.0).abs() < 0.001;
+///
+/// assert!(close(tuning.note_to_freq(Note(69)), 432.0));
+/// assert!(close(tuning.note_to_freq(Note(81)), 864.0));
+/// assert!(close(tuning.note_to_freq(Note(57)), 216.0));
+/// assert_eq!(Note(72), tuning.freq_to_note(Frequency(513.74)));
+///
+/// // 19 equal divisions of the octave
+/// let tuning = Tuning::new(440.0, Note(69), 19.0);
+///
+/// assert!(close(tuning.note_to_freq(Note(88)), 880.0));
+/// assert!(close(tuning.note_to_freq(Note(70)), 456.3));
+/// assert_eq!(Note(70), tuning.freq_to_note(Frequency(456.3)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tuning {
+    /// Frequency of the reference note
+    pub reference_freq: f32,
+    /// Reference note, which will sound at exactly `reference_freq`
+    pub reference_note: Note,
+    /// Number of notes per octave
+    pub divisions_per_octave: f32,
+}
+
+impl Tuning {
+    /// Constructor
+    pub fn new(reference_freq: f32, reference_note: Note, divisions_per_octave: f32) -> Self {
+        Self {
+            reference_freq,
+            reference_note,
+            divisions_per_octave,
+        }
+    }
+
+    /// Convert a note to a frequency
+    pub fn note_to_freq(self, note: impl Into<Note>) -> Frequency {
+        let octaves =
+            (note.into().0 as f32 - self.reference_note.0 as f32) / self.divisions_per_octave;
+        let freq: Frequency = Volts(octaves).into();
+        Frequency(freq.0 * self.reference_freq / 440.0)
+    }
+
+    /// Convert a frequency to the nearest note
+    pub fn freq_to_note(self, freq: impl Into<Frequency>) -> Note {
+        let octaves: Volts = Frequency(freq.into().0 * 440.0 / self.reference_freq).into();
+        let note = self.reference_note.0 as f32 + octaves.0 * self.divisions_per_octave;
+        Note((note + 0.5) as u8)
+    }
+}
+
+impl Default for Tuning {
+    /// 12 tone equal temperament, A4 = 440Hz
+    fn default() -> Self {
+        Self::new(440.0, Note(69), 12.0)
+    }
+}
+
+/// Musical scale, used to quantize notes to the nearest in-scale note
+///
+/// ```
+/// # use owl_patch::volts_per_octave::*;
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// # let (vps_in, vps_out) = pv.volts_per_sample();
+/// let scale = Scale::new(Note(60), &Scale::MAJOR);
+///
+/// let note = vps_in.sample_to_note(0.1);
+/// let out_sample = vps_out.note_to_sample(scale.quantize(note));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scale {
+    root: u8,
+    // bit n is set if the note n semitones above the root is in the scale
+    mask: u16,
+}
+
+impl Scale {
+    /// Major scale semitone offsets
+    pub const MAJOR: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+    /// Natural minor scale semitone offsets
+    pub const MINOR: [u8; 7] = [0, 2, 3, 5, 7, 8, 10];
+    /// Major pentatonic scale semitone offsets
+    pub const PENTATONIC: [u8; 5] = [0, 2, 4, 7, 9];
+
+    /// Create a scale from a root note and the semitone offsets (from the root) of each note in the scale.
+    ///
+    /// Offsets are taken modulo 12, and the root's octave doesn't matter.
+    pub fn new(root: impl Into<Note>, offsets: &[u8]) -> Self {
+        Self {
+            root: root.into().0 % 12,
+            mask: offsets
+                .iter()
+                .fold(0, |mask, offset| mask | (1 << (offset % 12))),
+        }
+    }
+
+    /// Is the note part of the scale?
+    pub fn contains(self, note: impl Into<Note>) -> bool {
+        let degree = (note.into().0 % 12 + 12 - self.root) % 12;
+        self.mask & (1 << degree) != 0
+    }
+
+    /// Snap a note to the nearest note in the scale. When two scale notes are equally near, the lower
+    /// one is chosen. An empty scale returns the note unchanged.
+    ///
+    /// ```
+    /// # use owl_patch::volts_per_octave::*;
+    /// let scale = Scale::new(Note(60), &Scale::MAJOR);
+    ///
+    /// assert_eq!(Note(60), scale.quantize(Note(60)));
+    /// assert_eq!(Note(60), scale.quantize(Note(61)));
+    /// assert_eq!(Note(64), scale.quantize(Note(64)));
+    /// assert_eq!(Note(65), scale.quantize(Note(65)));
+    /// assert_eq!(Note(69), scale.quantize(Note(70)));
+    /// assert_eq!(Note(71), scale.quantize(Note(71)));
+    /// assert_eq!(Note(72), scale.quantize(Note(72)));
+    ///
+    /// // out of scale notes near the root can snap across the octave boundary
+    /// let scale = Scale::new(Note(60), &Scale::PENTATONIC); // C pentatonic: C D E G A
+    /// assert_eq!(Note(72), scale.quantize(Note(71))); // B -> C above
+    /// assert_eq!(Note(60), scale.quantize(Note(59))); // B below the root -> C
+    /// assert_eq!(Note(69), scale.quantize(Note(70))); // Bb -> A
+    ///
+    /// let scale = Scale::new(Note(60), &[4, 7, 11]); // E G B, rooted at C
+    /// assert_eq!(Note(59), scale.quantize(Note(60))); // C -> B below
+    /// assert_eq!(Note(71), scale.quantize(Note(73))); // C# -> B below
+    ///
+    /// let scale = Scale::new(Note(62), &Scale::PENTATONIC); // D pentatonic: D E F# A B
+    /// assert_eq!(Note(74), scale.quantize(Note(73))); // C# -> D
+    /// assert_eq!(Note(71), scale.quantize(Note(72))); // C -> B
+    /// assert_eq!(Note(66), scale.quantize(Note(67))); // G -> F#
+    /// ```
+    pub fn quantize(self, note: impl Into<Note>) -> Note {
+        let note = note.into().0;
+        (0..=6)
+            .flat_map(|d| [note.checked_sub(d), note.checked_add(d)])
+            .flatten()
+            .find(|n| *n <= 127 && self.contains(*n))
+            .map_or(Note(note), Note)
+    }
+
+    /// Snap a voltage to the voltage of the nearest note in the scale
+    ///
+    /// ```
+    /// # use owl_patch::volts_per_octave::*;
+    /// let scale = Scale::new(Note(60), &Scale::MAJOR);
+    ///
+    /// assert_eq!(Volts::from(Note(64)), scale.quantize_volts(Volts(-0.42)));
+    /// ```
+    pub fn quantize_volts(self, volts: impl Into<Volts>) -> Volts {
+        // Round to the nearest semitone, as Volts -> Note rounds down
+        let note: Note = Volts(volts.into().0 + 1.0 / 24.0).into();
+        self.quantize(note).into()
+    }
+}
+
+/// Glide (portamento) for pitch CV. Moves towards a target voltage at a constant rate
+///
+/// ```
+/// # use owl_patch::volts_per_octave::*;
+/// # let mut pv = unsafe { owl_patch::test_harness::program_vector() };
+/// # let (_, vps_out) = pv.volts_per_sample();
+/// // 1 octave per 100ms
+/// let mut glide = Glide::new(10.0, 48000.0);
+/// glide.set_target(Note(72));
+///
+/// // per sample, in the audio loop
+/// let sample = vps_out.volts_to_sample(glide.process());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Glide {
+    current: f32,
+    target: f32,
+    step: f32,
+}
+
+impl Glide {
+    /// Create a new glide, moving at `rate` volts (octaves) per second. Starts at 0V
+    pub fn new(rate: f32, sample_rate: f32) -> Self {
+        let mut glide = Self::default();
+        glide.set_rate(rate, sample_rate);
+        glide
+    }
+
+    /// Change the rate, in volts (octaves) per second. A rate of zero or less disables gliding
+    pub fn set_rate(&mut self, rate: f32, sample_rate: f32) {
+        self.step = if rate > 0.0 {
+            rate / sample_rate
+        } else {
+            f32::INFINITY
+        };
+    }
+
+    /// Set a new destination. Gliding continues from the current value
+    pub fn set_target(&mut self, target: impl Into<Volts>) {
+        self.target = target.into().0;
+    }
+
+    /// Jump straight to a value, without gliding
+    pub fn reset(&mut self, value: impl Into<Volts>) {
+        self.current = value.into().0;
+        self.target = self.current;
+    }
+
+    /// Current value
+    pub fn value(&self) -> Volts {
+        Volts(self.current)
+    }
+
+    /// Advance by one sample, returning the new value
+    ///
+    /// ```
+    /// # use owl_patch::volts_per_octave::*;
+    /// // 1V per second at 1kHz, 1mV per sample
+    /// let mut glide = Glide::new(1.0, 1000.0);
+    /// glide.set_target(Volts(0.5));
+    ///
+    /// (0..499).for_each(|_| { glide.process(); });
+    /// assert!(glide.value().0 < 0.5);
+    /// // allow one extra sample for rounding errors
+    /// (0..2).for_each(|_| { glide.process(); });
+    /// assert_eq!(Volts(0.5), glide.value());
+    ///
+    /// // Change direction mid-glide
+    /// glide.set_target(Volts(-1.0));
+    /// (0..250).for_each(|_| { glide.process(); });
+    /// assert!((glide.value().0 - 0.25).abs() < 0.001);
+    /// glide.set_target(Volts(1.0));
+    /// (0..751).for_each(|_| { glide.process(); });
+    /// assert_eq!(Volts(1.0), glide.value());
+    /// ```
+    pub fn process(&mut self) -> Volts {
+        let delta = self.target - self.current;
+        self.current = if delta.abs() <= self.step {
+            self.target
+        } else if delta > 0.0 {
+            self.current + self.step
+        } else {
+            self.current - self.step
+        };
+        Volts(self.current)
+    }
+}